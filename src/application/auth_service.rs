@@ -18,4 +18,32 @@ impl AuthService {
     pub async fn check_auth_status(&self, pin_id: u64) -> AppResult<Option<PlexAuth>> {
         self.plex_client.check_oauth_status(pin_id).await
     }
+
+    /// Account the saved token belongs to, for configs saved before its ID was stored.
+    pub async fn get_account(&self, config: &AppConfig) -> AppResult<PlexAuth> {
+        match &config.auth_token {
+            Some(token) => self.plex_client.get_account(token).await,
+            None => Err(AppError::Auth("Not authenticated".into())),
+        }
+    }
+
+    pub async fn login_with_credentials(
+        &self,
+        login: &str,
+        password: &str,
+        verification_code: Option<String>,
+    ) -> AppResult<PlexAuth> {
+        if login.trim().is_empty() || password.is_empty() {
+            return Err(AppError::Auth("Username and password are required".into()));
+        }
+
+        // Blank 2FA field means the account doesn't use two-factor
+        let verification_code = verification_code
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty());
+
+        self.plex_client
+            .sign_in_with_credentials(login.trim(), password, verification_code)
+            .await
+    }
 }
@@ -0,0 +1,354 @@
+use crate::application::statistics::WatchStatistics;
+use crate::domain::models::*;
+use crate::domain::traits::HistoryStore;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Viewings shorter than this are skipped. Skimming through a playlist or checking
+/// which episode is next would otherwise fill the history and statistics with noise.
+const MIN_WATCHED: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+pub struct HistoryService {
+    store: Arc<dyn HistoryStore>,
+}
+
+impl HistoryService {
+    pub fn new(store: Arc<dyn HistoryStore>) -> Self {
+        Self { store }
+    }
+
+    /// Statistics over the whole history, grouped by local date.
+    pub fn statistics(&self) -> AppResult<WatchStatistics> {
+        let entries = self.store.load()?;
+        let now = jiff::Zoned::now();
+        Ok(WatchStatistics::from_entries(
+            &entries,
+            now.date(),
+            now.time_zone(),
+        ))
+    }
+
+    /// Writes the whole history to `path`. Returns the number of entries written.
+    pub fn export(&self, path: &Path, format: ExportFormat) -> AppResult<usize> {
+        let entries = self.store.load()?;
+        let data = match format {
+            ExportFormat::Csv => to_csv(&entries),
+            ExportFormat::Json => serde_json::to_string_pretty(&entries)
+                .map_err(|e| AppError::Config(format!("Failed to serialize history: {}", e)))?,
+        };
+        std::fs::write(path, data)?;
+        Ok(entries.len())
+    }
+
+    /// Records viewings from the monitor's events until the event channel closes.
+    pub fn spawn_recorder(
+        &self,
+        mut events: broadcast::Receiver<FilteredEvent>,
+        runtime: &tokio::runtime::Handle,
+    ) -> JoinHandle<()> {
+        let store = self.store.clone();
+        runtime.spawn(async move {
+            let mut recorder = HistoryRecorder::default();
+            loop {
+                let event = match events.recv().await {
+                    // The history keeps what was played, whatever Discord showed
+                    Ok(event) => event.event,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::warn!("History missed {} monitor events", missed);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let Some(entry) = recorder.handle(event) {
+                    if let Err(e) = store.append(&entry) {
+                        log::warn!("Failed to record '{}': {}", entry.session.media_title, e);
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// The viewing in progress, between its start and end events.
+struct Viewing {
+    started_at: SystemTime,
+    session: Session,
+    watched: Duration,
+    /// Set while playing, time since then isn't in `watched` yet
+    playing_since: Option<Instant>,
+}
+
+impl Viewing {
+    fn new(session: Session) -> Self {
+        let playing_since = (session.player_state == PlayerState::Playing).then(Instant::now);
+        Self {
+            started_at: SystemTime::now(),
+            session,
+            watched: Duration::ZERO,
+            playing_since,
+        }
+    }
+
+    fn update(&mut self, session: Session) {
+        if let Some(since) = self.playing_since.take() {
+            self.watched += since.elapsed();
+        }
+        if session.player_state == PlayerState::Playing {
+            self.playing_since = Some(Instant::now());
+        }
+        self.session = session;
+    }
+
+    fn finish(mut self) -> Option<WatchHistoryEntry> {
+        // The last event's offset is stale while playing, move it on by the time since
+        let playing_for = self.playing_since.map(|t| t.elapsed()).unwrap_or_default();
+        self.watched += playing_for;
+        if self.watched < MIN_WATCHED {
+            return None;
+        }
+
+        let position = self.session.view_offset + playing_for.as_millis() as u64;
+        let finished = self.session.duration > 0
+            && position as f64
+                >= self.session.duration as f64 * WatchHistoryEntry::FINISHED_THRESHOLD;
+
+        let mut session = self.session;
+        session.view_offset = if session.duration > 0 {
+            position.min(session.duration)
+        } else {
+            position
+        };
+
+        Some(WatchHistoryEntry {
+            started_at: unix_secs(self.started_at),
+            ended_at: unix_secs(SystemTime::now()),
+            watched_ms: self.watched.as_millis() as u64,
+            finished,
+            session,
+        })
+    }
+}
+
+#[derive(Default)]
+struct HistoryRecorder {
+    current: Option<Viewing>,
+}
+
+impl HistoryRecorder {
+    /// Returns the viewing this event completed, if any.
+    fn handle(&mut self, event: MonitorEvent) -> Option<WatchHistoryEntry> {
+        match event {
+            MonitorEvent::SessionStarted(session) | MonitorEvent::MediaChanged(session) => self
+                .current
+                .replace(Viewing::new(session))
+                .and_then(Viewing::finish),
+            MonitorEvent::Paused(session)
+            | MonitorEvent::Resumed(session)
+            | MonitorEvent::Seeked(session)
+            | MonitorEvent::Buffering(session) => {
+                match &mut self.current {
+                    Some(viewing) => viewing.update(session),
+                    // Started before the recorder subscribed
+                    None => self.current = Some(Viewing::new(session)),
+                }
+                None
+            }
+            MonitorEvent::Ended(session) => {
+                let mut viewing = self.current.take()?;
+                viewing.update(session);
+                viewing.finish()
+            }
+            MonitorEvent::DiscordDisconnected { .. }
+            | MonitorEvent::ServerUnreachable { .. }
+            | MonitorEvent::IncognitoEnded => None,
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+fn to_csv(entries: &[WatchHistoryEntry]) -> String {
+    let mut out = String::from(
+        "started_at,ended_at,watched_seconds,finished,type,title,show_or_artist,season_or_album,\
+         season,episode,year,library,duration_seconds,content_rating,studio,genres,guids,rating_key\n",
+    );
+    // RFC 3339 in UTC, e.g. `2026-01-24T16:00:00Z`
+    let format_time = |unix_secs: u64| {
+        i64::try_from(unix_secs)
+            .ok()
+            .and_then(|secs| jiff::Timestamp::from_second(secs).ok())
+            .map(|time| time.to_string())
+            .unwrap_or_default()
+    };
+    for entry in entries {
+        let s = &entry.session;
+        let fields = [
+            format_time(entry.started_at),
+            format_time(entry.ended_at),
+            (entry.watched_ms / 1000).to_string(),
+            entry.finished.to_string(),
+            format!("{:?}", s.media_type).to_lowercase(),
+            s.media_title.clone(),
+            s.media_grandparent_title.clone().unwrap_or_default(),
+            s.media_parent_title.clone().unwrap_or_default(),
+            optional_number(s.media_parent_index),
+            optional_number(s.media_index),
+            optional_number(s.year),
+            s.library_section_title.clone().unwrap_or_default(),
+            (s.duration / 1000).to_string(),
+            s.content_rating.clone().unwrap_or_default(),
+            s.studio.clone().unwrap_or_default(),
+            s.genres.join("; "),
+            s.guids.join(" "),
+            s.rating_key.clone().unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn optional_number(value: Option<u32>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewing(session: Session, watched: Duration) -> Viewing {
+        Viewing {
+            started_at: SystemTime::now() - watched,
+            session,
+            watched,
+            playing_since: None,
+        }
+    }
+
+    fn movie(view_offset: u64) -> Session {
+        let mut session = Session::for_test(MediaType::Movie, "The Matrix");
+        session.duration = 8_160_000;
+        session.view_offset = view_offset;
+        session.player_state = PlayerState::Paused;
+        session
+    }
+
+    #[test]
+    fn short_viewings_are_skipped() {
+        assert!(viewing(movie(0), Duration::from_secs(29))
+            .finish()
+            .is_none());
+        assert!(viewing(movie(0), Duration::from_secs(30))
+            .finish()
+            .is_some());
+    }
+
+    #[test]
+    fn finished_past_the_threshold() {
+        let threshold = (8_160_000.0 * WatchHistoryEntry::FINISHED_THRESHOLD) as u64;
+
+        let entry = viewing(movie(threshold - 1000), Duration::from_secs(600))
+            .finish()
+            .unwrap();
+        assert!(!entry.finished);
+
+        let entry = viewing(movie(threshold), Duration::from_secs(600))
+            .finish()
+            .unwrap();
+        assert!(entry.finished);
+        assert_eq!(entry.watched_ms, 600_000);
+        assert_eq!(entry.ended_at - entry.started_at, 600);
+    }
+
+    #[test]
+    fn playing_time_counts_until_the_end() {
+        let mut session = movie(8_150_000);
+        session.player_state = PlayerState::Playing;
+        let mut viewing = viewing(session, Duration::from_secs(60));
+        viewing.playing_since = Some(Instant::now() - Duration::from_secs(20));
+
+        let entry = viewing.finish().unwrap();
+
+        assert!(entry.watched_ms >= 80_000);
+        // Moved on by the time played since the last event, but not past the end
+        assert_eq!(entry.session.view_offset, 8_160_000);
+        assert!(entry.finished);
+    }
+
+    #[test]
+    fn without_a_duration_nothing_is_finished() {
+        let mut session = movie(7_000_000);
+        session.duration = 0;
+
+        let entry = viewing(session, Duration::from_secs(600)).finish().unwrap();
+
+        assert!(!entry.finished);
+        assert_eq!(entry.session.view_offset, 7_000_000);
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let mut session = movie(0);
+        session.media_title = "Crouching Tiger, Hidden Dragon".to_string();
+        session.studio = Some("The \"Good\" Studio".to_string());
+        session.summary = Some("not exported".to_string());
+        session.library_section_title = Some("Movies\nand more".to_string());
+        session.genres = vec!["Action".to_string(), "Drama".to_string()];
+        let entry = WatchHistoryEntry {
+            started_at: 1_769_270_400,
+            ended_at: 1_769_277_600,
+            watched_ms: 7_000_500,
+            finished: true,
+            session,
+        };
+
+        let csv = to_csv(&[entry]);
+        let row = csv.split_once('\n').unwrap().1;
+
+        assert_eq!(
+            row,
+            "2026-01-24T16:00:00Z,2026-01-24T18:00:00Z,7000,true,movie,\
+             \"Crouching Tiger, Hidden Dragon\",,,,,,\"Movies\nand more\",8160,,\
+             \"The \"\"Good\"\" Studio\",Action; Drama,,100\n"
+        );
+    }
+}
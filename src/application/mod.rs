@@ -1,2 +1,6 @@
 pub mod auth_service;
+pub mod history_service;
 pub mod monitor_service;
+pub mod monitor_task;
+pub mod server_service;
+pub mod statistics;
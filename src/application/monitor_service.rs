@@ -1,78 +1,595 @@
+use crate::application::server_service::probe_connections;
 use crate::domain::models::*;
-use crate::domain::traits::{DiscordClient, PlexClient};
+use crate::domain::privacy::{generic_session, FilterAction};
+use crate::domain::traits::{ArtworkResolver, DiscordClient, PlexClient};
+use futures_util::future::{join_all, select_all};
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
+/// Minimum time between automatic re-probes of a server's connections.
+const REPROBE_COOLDOWN: Duration = Duration::from_secs(30);
+/// How long the players looked up on plex.tv are trusted before they are fetched again.
+const PLAYERS_REFRESH: Duration = Duration::from_secs(600);
+
+/// Monitoring pipeline for one server, with its own polling or WebSocket strategy.
+struct ServerMonitor {
+    server: PlexServer,
+    last_probe: Option<Instant>,
+    notification_rx: Option<tokio::sync::mpsc::Receiver<PlexNotification>>,
+    /// Notifications already taken off `notification_rx` while waiting for one
+    pending_notifications: Vec<PlexNotification>,
+    /// Active (non-idle) session of the user on this server
+    session: Option<Session>,
+    /// When `session` started, used by `MultiServerPolicy::MostRecentlyStarted`
+    started_at: Option<Instant>,
+    /// When each of the user's sessions on this server was first seen, by `session_id`
+    first_seen: HashMap<String, Instant>,
+    /// Last refresh failed, `ServerUnreachable` is only published on the transition
+    unreachable: bool,
+}
+
+impl ServerMonitor {
+    fn new(server: PlexServer) -> Self {
+        Self {
+            server,
+            last_probe: None,
+            notification_rx: None,
+            pending_notifications: Vec::new(),
+            session: None,
+            started_at: None,
+            first_seen: HashMap::new(),
+            unreachable: false,
+        }
+    }
+
+    /// Refreshes the session, switching to another connection if the current one
+    /// stopped working.
+    async fn refresh(
+        &mut self,
+        plex_client: &dyn PlexClient,
+        token: &str,
+        matcher: &SessionMatcher,
+        selection: &SessionSelection,
+        players: &HashMap<String, PlayerInfo>,
+    ) -> AppResult<()> {
+        let error = match self
+            .refresh_session(plex_client, token, matcher, selection, players)
+            .await
+        {
+            Err(e @ AppError::Network(_)) => e,
+            other => return other,
+        };
+
+        let can_reprobe = self.server.connections.len() > 1
+            && self
+                .last_probe
+                .is_none_or(|t| t.elapsed() > REPROBE_COOLDOWN);
+        if !can_reprobe {
+            return Err(error);
+        }
+
+        self.last_probe = Some(Instant::now());
+        match probe_connections(plex_client, &self.server, token).await {
+            Ok(server) if server.base_url() != self.server.base_url() => {
+                log::info!(
+                    "{} unreachable at {}, switching to {}",
+                    self.server.name,
+                    self.server.base_url(),
+                    server.base_url()
+                );
+                self.server = server;
+                self.notification_rx = None;
+                self.refresh_session(plex_client, token, matcher, selection, players)
+                    .await
+            }
+            _ => Err(error),
+        }
+    }
+
+    async fn refresh_session(
+        &mut self,
+        plex_client: &dyn PlexClient,
+        token: &str,
+        matcher: &SessionMatcher,
+        selection: &SessionSelection,
+        players: &HashMap<String, PlayerInfo>,
+    ) -> AppResult<()> {
+        // Shared servers usually answer /status/sessions with 401 for non-admins,
+        // so they are driven by WebSocket notifications plus /library/metadata.
+        let mut use_polling = self.server.owned;
+
+        // Latest notification for the session we already track, applied without a refetch
+        let mut tracked_update: Option<PlaySessionStateNotification> = None;
+        // Latest notification for any other session, resolved through its metadata
+        let mut new_session_update: Option<PlaySessionStateNotification> = None;
+
+        if !self.server.owned {
+            if self.notification_rx.is_none() {
+                // Try to connect
+                match plex_client
+                    .listen_for_notifications(&self.server, token)
+                    .await
+                {
+                    Ok(rx) => self.notification_rx = Some(rx),
+                    Err(e) => {
+                        eprintln!("Failed to connect to Plex notification socket: {}. Falling back to polling.", e);
+                        // If WS fails, fallback to polling
+                        use_polling = true;
+                    }
+                }
+            }
+
+            let mut notifications = std::mem::take(&mut self.pending_notifications);
+            if let Some(rx) = &mut self.notification_rx {
+                loop {
+                    match rx.try_recv() {
+                        Ok(notification) => notifications.push(notification),
+                        Err(tokio::sync::mpsc::error::TryRecvError::Empty) => break,
+                        Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
+                            eprintln!(
+                                "Plex notification socket disconnected. triggering reconnect."
+                            );
+                            // The next update call will reconnect
+                            self.notification_rx = None;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            for notification in notifications {
+                match notification {
+                    PlexNotification::PlaySessionState(notification) => {
+                        let is_tracked = self.session.as_ref().is_some_and(|s| {
+                            s.session_key.as_deref() == Some(notification.session_key.as_str())
+                                && (notification.rating_key.is_none()
+                                    || s.rating_key == notification.rating_key)
+                        });
+                        // Notifications cover every user of the server and only name the
+                        // player, so only players registered with the account are followed
+                        let account_player = notification
+                            .client_identifier
+                            .as_ref()
+                            .and_then(|id| players.get(id));
+                        if is_tracked {
+                            tracked_update = Some(notification);
+                        } else if notification.state != PlayerState::Idle
+                            && account_player.is_some_and(|p| matcher.matches_player(Some(p)))
+                        {
+                            new_session_update = Some(notification);
+                        }
+                    }
+                    // Library scans, activity and timeline traffic
+                    PlexNotification::Other(_) => {}
+                }
+            }
+        }
+
+        let session = if use_polling {
+            let sessions = plex_client
+                .get_sessions(&self.server, token, matcher)
+                .await?;
+            self.select(sessions, selection)
+        } else if let Some(notification) = new_session_update {
+            let new_session = plex_client
+                .get_session_from_notification(&self.server, token, &notification)
+                .await?
+                .map(|mut session| {
+                    session.player = identify_player(session.player.take(), players);
+                    session
+                });
+            // Notifications come one session at a time, the tracked one competes with it
+            let candidates = self
+                .session
+                .clone()
+                .into_iter()
+                .chain(new_session)
+                .collect();
+            self.select(candidates, selection)
+        } else if let Some(notification) = tracked_update {
+            // The notification carries state and offset, the rest is unchanged
+            self.session.clone().map(|mut session| {
+                session.player_state = notification.state;
+                session.view_offset = notification.view_offset;
+                session
+            })
+        } else {
+            // Nothing relevant happened, keep the last known session
+            return Ok(());
+        };
+
+        // Treat Idle state as no active session
+        self.set_session(session.filter(|s| s.player_state != PlayerState::Idle));
+        Ok(())
+    }
+
+    /// Picks the session to show among the user's `sessions` on this server.
+    fn select(&mut self, sessions: Vec<Session>, selection: &SessionSelection) -> Option<Session> {
+        let now = Instant::now();
+        let active: Vec<Session> = sessions
+            .into_iter()
+            .filter(|s| s.player_state != PlayerState::Idle)
+            .collect();
+        self.first_seen
+            .retain(|id, _| active.iter().any(|s| session_id(s) == *id));
+        for session in &active {
+            self.first_seen.entry(session_id(session)).or_insert(now);
+        }
+
+        let current = self.session.as_ref().map(session_id);
+        active.into_iter().min_by_key(|session| {
+            let id = session_id(session);
+            let kept = selection.policy == SessionPolicy::Sticky && current.as_ref() == Some(&id);
+            let is_paused = session.player_state == PlayerState::Paused;
+            let is_music = selection.prefer_video && session.media_type == MediaType::Track;
+            let device_rank = match selection.policy {
+                SessionPolicy::DeviceOrder => session
+                    .player
+                    .as_ref()
+                    .and_then(|player| {
+                        selection
+                            .device_order
+                            .iter()
+                            .position(|device| player.matches(device))
+                    })
+                    .unwrap_or(selection.device_order.len()),
+                _ => 0,
+            };
+            // Sessions seen in the same poll fall back to the session key, which
+            // the server hands out in increasing order
+            let started = (
+                self.first_seen.get(&id).copied(),
+                session
+                    .session_key
+                    .as_deref()
+                    .and_then(|k| k.parse::<u64>().ok()),
+            );
+            (
+                !kept,
+                is_paused,
+                is_music,
+                device_rank,
+                std::cmp::Reverse(started),
+            )
+        })
+    }
+
+    /// Driven by WebSocket notifications rather than polling.
+    fn is_notified(&self) -> bool {
+        self.notification_rx.is_some() || !self.pending_notifications.is_empty()
+    }
+
+    fn set_session(&mut self, session: Option<Session>) {
+        let is_same_media = match (&self.session, &session) {
+            (Some(last), Some(new)) => last.is_same_media(new),
+            _ => false,
+        };
+        if !is_same_media {
+            self.started_at = session.as_ref().map(|_| Instant::now());
+        }
+        self.session = session;
+    }
+}
+
+/// Fills in what plex.tv knows about a player that came with nothing but its
+/// machine identifier, as players on shared servers do.
+fn identify_player(
+    player: Option<PlayerInfo>,
+    players: &HashMap<String, PlayerInfo>,
+) -> Option<PlayerInfo> {
+    let player = player?;
+    match player
+        .machine_identifier
+        .as_ref()
+        .and_then(|id| players.get(id))
+    {
+        Some(known) => Some(known.clone()),
+        None => Some(player),
+    }
+}
+
+/// How the privacy filters or incognito keep `session` off Discord, `None` to show it.
+fn presence_filter(config: &AppConfig, incognito: bool, session: &Session) -> Option<FilterAction> {
+    if incognito {
+        Some(FilterAction::Hide)
+    } else {
+        config.privacy.evaluate(session)
+    }
+}
+
+/// Identifies a playback session across refreshes.
+fn session_id(session: &Session) -> String {
+    match &session.session_key {
+        Some(key) => key.clone(),
+        None => format!(
+            "{}|{}",
+            session.rating_key.as_deref().unwrap_or_default(),
+            session.media_title
+        ),
+    }
+}
+
+/// Tracks `AppConfig::incognito` across refreshes.
+#[derive(Default)]
+struct IncognitoState {
+    /// Config value the fields below belong to
+    config: Incognito,
+    /// The item of `Incognito::UntilMediaEnds` has been seen playing
+    media_seen: bool,
+    /// Ran out, stays off until the config changes
+    ended: bool,
+    active: bool,
+}
+
+impl IncognitoState {
+    /// Returns true when a timed incognito just ran out. `now` is in unix seconds.
+    fn update(&mut self, config: &Incognito, playing: Option<&Session>, now: u64) -> bool {
+        if *config != self.config {
+            *self = IncognitoState {
+                config: config.clone(),
+                ..IncognitoState::default()
+            };
+        }
+        if self.ended {
+            return false;
+        }
+
+        self.active = match config {
+            Incognito::Off => false,
+            Incognito::On => true,
+            Incognito::Until { until } => now < *until,
+            Incognito::UntilMediaEnds {
+                rating_key,
+                media_title,
+            } => match playing {
+                Some(s) if s.rating_key == *rating_key && s.media_title == *media_title => {
+                    self.media_seen = true;
+                    true
+                }
+                Some(_) => false,
+                // Either not known yet after a restart, or it has stopped
+                None => !self.media_seen,
+            },
+        };
+        self.ended = !self.active && *config != Incognito::Off;
+        self.ended
+    }
+}
+
 pub struct MonitorService {
     plex_client: Arc<dyn PlexClient>,
     discord_client: Arc<Mutex<dyn DiscordClient>>,
+    artwork: Arc<dyn ArtworkResolver>,
+    servers: Vec<ServerMonitor>,
+    /// Token that Plex rejected, monitoring stays stopped until it changes
+    revoked_token: Option<String>,
     last_session: Option<Session>,
     idle_since: Option<Instant>,
-    notification_rx: Option<tokio::sync::mpsc::Receiver<()>>,
     last_update_time: Option<Instant>,
+    /// Last presence update failed, retried on every refresh until it works
+    discord_failed: bool,
+    /// Privacy filter applied to the presence of `last_session`
+    presence_filter: Option<FilterAction>,
+    /// Artwork shown with the presence of `last_session`
+    shown_artwork: Option<String>,
+    incognito: IncognitoState,
+    /// Players registered with the account on plex.tv by machine identifier, the only
+    /// ones followed on shared servers
+    players: HashMap<String, PlayerInfo>,
+    players_fetched_at: Option<Instant>,
 }
 
 impl MonitorService {
     pub fn new(
         plex_client: Arc<dyn PlexClient>,
         discord_client: Arc<Mutex<dyn DiscordClient>>,
+        artwork: Arc<dyn ArtworkResolver>,
     ) -> Self {
         Self {
             plex_client,
             discord_client,
+            artwork,
+            servers: Vec::new(),
+            revoked_token: None,
             last_session: None,
             idle_since: None,
-            notification_rx: None,
             last_update_time: None,
+            discord_failed: false,
+            presence_filter: None,
+            shown_artwork: None,
+            incognito: IncognitoState::default(),
+            players: HashMap::new(),
+            players_fetched_at: None,
         }
     }
 
-    pub async fn clear_state(&mut self) -> AppResult<()> {
+    /// Clears the presence and forgets all servers. Ends the shown session, if any.
+    pub async fn clear_state(&mut self) -> Vec<FilteredEvent> {
         let mut discord = self.discord_client.lock().await;
         // Ignore error on clear, we just want to try
         let _ = discord.clear_presence();
-        self.last_session = None;
+        self.servers.clear();
+        self.players_fetched_at = None;
         self.idle_since = None;
-        self.notification_rx = None;
         self.last_update_time = None;
-        Ok(())
+        self.discord_failed = false;
+        self.shown_artwork = None;
+        let filter = self.presence_filter.take();
+        self.last_session
+            .take()
+            .map(|session| MonitorEvent::Ended(session).filtered(filter))
+            .into_iter()
+            .collect()
     }
 
-    pub async fn update(&mut self, config: &AppConfig) -> AppResult<String> {
-        if !config.is_authenticated() {
-            let mut discord = self.discord_client.lock().await;
-            let _ = discord.clear_presence();
-            return Ok("Not authenticated".to_string());
+    /// Clears the presence without an active session, ending the shown one if any.
+    async fn clear_presence(&mut self) -> Vec<FilteredEvent> {
+        let mut discord = self.discord_client.lock().await;
+        let _ = discord.clear_presence();
+        self.idle_since = None;
+        self.shown_artwork = None;
+        let filter = self.presence_filter.take();
+        self.last_session
+            .take()
+            .map(|session| MonitorEvent::Ended(session).filtered(filter))
+            .into_iter()
+            .collect()
+    }
+
+    /// Keeps one monitor per configured server, in config order, reusing existing ones.
+    /// A reused monitor keeps the connection it switched to at runtime.
+    fn sync_servers(&mut self, servers: &[PlexServer]) {
+        let mut existing = std::mem::take(&mut self.servers);
+        self.servers = servers
+            .iter()
+            .map(|server| {
+                match existing
+                    .iter()
+                    .position(|m| m.server.is_same_server(server) && m.server.owned == server.owned)
+                {
+                    Some(idx) => {
+                        let mut monitor = existing.swap_remove(idx);
+                        monitor.server.name = server.name.clone();
+                        monitor
+                    }
+                    None => ServerMonitor::new(server.clone()),
+                }
+            })
+            .collect();
+    }
+
+    /// Looks up the account's players on plex.tv for shared servers, whose notifications
+    /// cover every user and only name the player by its machine identifier.
+    async fn refresh_players(&mut self, token: &str) {
+        if self.servers.iter().all(|m| m.server.owned)
+            || self
+                .players_fetched_at
+                .is_some_and(|t| t.elapsed() < PLAYERS_REFRESH)
+        {
+            return;
         }
 
-        let token = config.auth_token.as_ref().unwrap();
-        let username = config.username.as_ref().unwrap();
-
-        let server_address = match &config.server_address {
-            Some(a) => a,
-            None => {
-                let mut discord = self.discord_client.lock().await;
-                let _ = discord.clear_presence();
-                return Ok("No server selected".to_string());
+        self.players_fetched_at = Some(Instant::now());
+        match self.plex_client.get_players(token).await {
+            Ok(players) => {
+                self.players = players
+                    .into_iter()
+                    .filter_map(|p| Some((p.machine_identifier.clone()?, p)))
+                    .collect();
             }
-        };
+            Err(e) => log::warn!("Failed to look up players on plex.tv: {}", e),
+        }
+    }
+
+    /// Picks the session shown on Discord. Playing or buffering beats paused,
+    /// then the configured policy decides.
+    fn select_session(&self, policy: MultiServerPolicy) -> Option<Session> {
+        self.servers
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, monitor)| {
+                let session = monitor.session.as_ref()?;
+                let is_paused = session.player_state == PlayerState::Paused;
+                let rank = match policy {
+                    // Latest start sorts first
+                    MultiServerPolicy::MostRecentlyStarted => {
+                        (is_paused, std::cmp::Reverse(monitor.started_at), idx)
+                    }
+                    MultiServerPolicy::ServerOrder => (is_paused, std::cmp::Reverse(None), idx),
+                };
+                Some((rank, session))
+            })
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, session)| session.clone())
+    }
 
-        let server_port = match config.server_port {
-            Some(p) => p,
-            None => {
-                let mut discord = self.discord_client.lock().await;
-                let _ = discord.clear_presence();
-                return Ok("No server selected".to_string());
+    /// Position jumped more than network jitter explains since the last update.
+    fn is_seek(&self, last: &Session, session: &Session) -> bool {
+        let expected_offset = match (&session.player_state, self.last_update_time) {
+            (PlayerState::Playing, Some(last_time)) => {
+                last.view_offset + last_time.elapsed().as_millis() as u64
             }
+            (PlayerState::Paused, _) => last.view_offset,
+            _ => return false,
         };
+        // Use 3s threshold (3000ms) to allow minor network jitter but catch seeks
+        let drift = session.view_offset.abs_diff(expected_offset);
+        if drift > 3000 {
+            log::debug!(
+                "Detected seek: drift {}ms (expected {}, got {})",
+                drift,
+                expected_offset,
+                session.view_offset
+            );
+            return true;
+        }
+        false
+    }
+    /// Waits until any server's notification socket delivers something, or closes.
+    /// Never resolves while no socket is open. Cancel safe.
+    pub async fn wait_for_notification(&mut self) {
+        let receivers: Vec<_> = self
+            .servers
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(idx, monitor)| {
+                let rx = monitor.notification_rx.as_mut()?;
+                Some(Box::pin(async move { (idx, rx.recv().await) }))
+            })
+            .collect();
+        if receivers.is_empty() {
+            return std::future::pending().await;
+        }
 
-        let server = PlexServer {
-            name: config.server_name.clone().unwrap_or_default(),
-            address: server_address.clone(),
-            port: server_port,
-            owned: config.is_owned.unwrap_or(false),
-        };
+        let ((idx, notification), _, _) = select_all(receivers).await;
+        let monitor = &mut self.servers[idx];
+        match notification {
+            Some(notification) => monitor.pending_notifications.push(notification),
+            // Reconnected by the next refresh
+            None => monitor.notification_rx = None,
+        }
+    }
+
+    /// Refreshes every server, polling the ones without a notification socket.
+    ///
+    /// On `AppError::Unauthorized` the shown session is left as is, `clear_state`
+    /// ends it once the caller has stopped monitoring.
+    pub async fn update(&mut self, config: &AppConfig) -> AppResult<Vec<FilteredEvent>> {
+        self.refresh(config, true).await
+    }
+
+    /// Applies received notifications without polling the other servers.
+    pub async fn handle_notifications(
+        &mut self,
+        config: &AppConfig,
+    ) -> AppResult<Vec<FilteredEvent>> {
+        self.refresh(config, false).await
+    }
+
+    async fn refresh(
+        &mut self,
+        config: &AppConfig,
+        include_polled: bool,
+    ) -> AppResult<Vec<FilteredEvent>> {
+        if !config.is_authenticated() {
+            return Ok(self.clear_presence().await);
+        }
+
+        let token = config.auth_token.as_ref().unwrap();
+        let matcher = &config.session_matcher().unwrap();
+        let selection = &config.session_selection;
+
+        if self.revoked_token.as_ref() == Some(token) {
+            return Err(AppError::Unauthorized("Sign in again to resume".into()));
+        }
+
+        if config.servers.is_empty() {
+            return Ok(self.clear_presence().await);
+        }
+
+        self.sync_servers(&config.servers);
 
         // IMPORTANT: Try to connect to Discord early if not already connected
         {
@@ -85,196 +602,663 @@ impl MonitorService {
             }
         }
 
-        // Decision: Should we poll?
-        let should_poll = if server.owned {
-            // Owned servers: polling is reliable and standard
-            true
-        } else {
-            // Shared servers: use WebSocket to avoid ban/spam
-            if self.notification_rx.is_none() {
-                // Try to connect
-                match self
-                    .plex_client
-                    .listen_for_notifications(&server, token, username)
-                    .await
-                {
-                    Ok(rx) => {
-                        self.notification_rx = Some(rx);
-                        // Poll immediately on connect
-                        true
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to connect to Plex notification socket: {}. Falling back to polling.", e);
-                        // If WS fails, fallback to polling
-                        true
-                    }
+        self.refresh_players(token).await;
+
+        let plex_client = self.plex_client.clone();
+        let players = &self.players;
+        let results = join_all(self.servers.iter_mut().map(|monitor| {
+            let refresh = include_polled || monitor.is_notified();
+            let plex_client = plex_client.clone();
+            async move {
+                if refresh {
+                    monitor
+                        .refresh(plex_client.as_ref(), token, matcher, selection, players)
+                        .await
+                } else {
+                    Ok(())
                 }
-            } else {
-                // Check if we received notification
-                let mut received = false;
-                if let Some(rx) = &mut self.notification_rx {
-                    loop {
-                        match rx.try_recv() {
-                            Ok(_) => received = true,
-                            Err(tokio::sync::mpsc::error::TryRecvError::Empty) => break,
-                            Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
-                                eprintln!(
-                                    "Plex notification socket disconnected. triggering reconnect."
-                                );
-                                self.notification_rx = None;
-                                received = true;
-                                break;
+            }
+        }))
+        .await;
+
+        let mut events = Vec::new();
+        let incognito = self.incognito.active;
+        for (monitor, result) in self.servers.iter_mut().zip(results) {
+            match result {
+                Ok(()) => monitor.unreachable = false,
+                Err(e @ AppError::Unauthorized(_)) => {
+                    // The token is dead for every server, stop polling altogether. The
+                    // caller clears the state, which publishes the end of the shown session.
+                    log::warn!("{} rejected the Plex token: {}", monitor.server.name, e);
+                    self.revoked_token = Some(token.clone());
+                    return Err(e);
+                }
+                Err(e) => {
+                    log::warn!("Failed to refresh {}: {}", monitor.server.name, e);
+                    if !monitor.unreachable {
+                        monitor.unreachable = true;
+                        let filter = monitor
+                            .session
+                            .as_ref()
+                            .and_then(|s| presence_filter(config, incognito, s));
+                        events.push(
+                            MonitorEvent::ServerUnreachable {
+                                server: monitor.server.name.clone(),
+                                error: e.to_string(),
+                                session: monitor.session.clone(),
                             }
-                        }
+                            .filtered(filter),
+                        );
                     }
                 }
-
-                // If we just reset rx to None, we want to try reconnect immediately?
-                // The next update call will handle reconnection since rx is None.
-                // But we returned `received = true` so effective_poll becomes true, which is fine,
-                // it will try to fetch sessions which might fail if network is down, but that's handled.
-                received
-            }
-        };
-
-        // Also poll if we have no last session (initial load) or periodic update is needed?
-        // Actually, for shared servers, if we just connected, we return true.
-        // If we have a session playing, we might want to poll to update viewOffset drifting anyway?
-        // Spec says: "Emit only on change (compare all fields, 5s viewOffset drift)"
-        // If we rely purely on WS for shared, we might miss progress updates if they don't send socket events for every second.
-        // Plex WS sends "playing" notification periodically? No, usually state changes.
-        // But for "Watching", we need timestamps. Timestamps are calculated once.
-        // So we only need to update if player state changes or media changes.
-        // So WS trigger is sufficient.
-
-        // However, user might have just selected the server.
-        // Let's poll if `last_session` is None to ensure we get initial state.
-        let effective_poll = should_poll || self.last_session.is_none();
-
-        if !effective_poll {
-            // Just return last status or "Monitoring (Shared)"
-            if let Some(s) = &self.last_session {
-                return Ok(format!("Playing: {}", s.media_title)); // Approximation
-            } else {
-                return Ok("Monitoring (Idle)".to_string());
             }
         }
 
-        let fetch_result = self
-            .plex_client
-            .get_sessions(&server, token, username)
-            .await;
-
-        // Treat Idle state as no active session
-        let active_session = match fetch_result {
-            Ok(Some(session)) if session.player_state == PlayerState::Idle => None,
-            Ok(other) => other,
-            Err(e) => return Err(e),
-        };
-
-        match active_session {
-            Some(session) => {
-                // Active session found, reset idle timer
-                self.idle_since = None;
-
-                let mut status_msg = format!("Playing: {}", session.media_title);
-
-                // Check if session changed or needs update
-                // Simple logic: always update discord if playing, or if state changed, etc.
-                // Optimally we diff.
-                let should_update = if let Some(last) = &self.last_session {
-                    let mut changed = last.media_title != session.media_title
-                        || last.player_state != session.player_state
-                        || last.media_type != session.media_type;
-
-                    // Check for seek (drift) if playing
-                    if !changed && session.player_state == PlayerState::Playing {
-                        if let Some(last_time) = self.last_update_time {
-                            let elapsed_ms = last_time.elapsed().as_millis() as u64;
-                            let expected_offset = last.view_offset + elapsed_ms;
-                            // Use 3s threshold (3000ms) to allow minor network jitter but catch seeks
-                            let drift = if session.view_offset > expected_offset {
-                                session.view_offset - expected_offset
-                            } else {
-                                expected_offset - session.view_offset
-                            };
-
-                            if drift > 3000 {
-                                changed = true;
-                                log::debug!(
-                                    "Detected seek: drift {}ms (expected {}, got {})",
-                                    drift,
-                                    expected_offset,
-                                    session.view_offset
-                                );
-                            }
-                        }
-                    }
-                    changed
-                } else {
-                    true
-                };
+        let selected = self.select_session(config.multi_server_policy);
+        // The shown session is kept through the idle debounce, so is the item incognito waits for
+        let playing = selected.as_ref().or(self.last_session.as_ref());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        if self.incognito.update(&config.incognito, playing, now) {
+            events.push(MonitorEvent::IncognitoEnded.filtered(None));
+        }
 
-                if should_update {
-                    let mut discord = self.discord_client.lock().await;
-                    // Ensure connected
-                    if !discord.is_connected() {
-                        let _ = discord.connect(); // Try connect, ignore error for now log internally
-                    }
-                    if let Err(e) = discord.update_presence(&session) {
-                        return Ok(format!("Failed to update Discord: {:?}", e));
+        let Some(session) = selected else {
+            if self.last_session.is_some() {
+                // Wait a few seconds before clearing, playback may just be switching items
+                match self.idle_since {
+                    None => self.idle_since = Some(Instant::now()),
+                    Some(start_time) if start_time.elapsed() > Duration::from_secs(3) => {
+                        events.extend(self.clear_presence().await);
                     }
+                    Some(_) => {}
                 }
+            }
+            return Ok(events);
+        };
 
-                match session.player_state {
-                    PlayerState::Paused => status_msg = format!("Paused: {}", session.media_title),
-                    PlayerState::Buffering => {
-                        status_msg = format!("Buffering: {}", session.media_title)
-                    }
-                    _ => {}
-                }
+        // Active session found, reset idle timer
+        self.idle_since = None;
 
-                self.last_session = Some(session);
-                self.last_update_time = Some(Instant::now());
-                Ok(status_msg)
+        let event: Option<fn(Session) -> MonitorEvent> = match &self.last_session {
+            None => Some(MonitorEvent::SessionStarted),
+            Some(last)
+                if !last.is_same_media(&session) || last.media_type != session.media_type =>
+            {
+                Some(MonitorEvent::MediaChanged)
             }
-            None => {
-                if self.last_session.is_some() {
-                    match self.idle_since {
-                        None => {
-                            // First time realizing we are idle, start timer
-                            self.idle_since = Some(Instant::now());
-                            Ok("Waiting for idle debounce...".to_string())
-                        }
-                        Some(start_time) => {
-                            if start_time.elapsed() > Duration::from_secs(3) {
-                                // Debounce passed, clear presence
-                                let mut discord = self.discord_client.lock().await;
-                                let _ = discord.clear_presence();
-                                self.last_session = None;
-                                self.idle_since = None;
-                                Ok("No active session".to_string())
-                            } else {
-                                Ok("Waiting for idle debounce...".to_string())
+            Some(last) if last.player_state != session.player_state => match session.player_state {
+                PlayerState::Playing => Some(MonitorEvent::Resumed),
+                PlayerState::Paused => Some(MonitorEvent::Paused),
+                PlayerState::Buffering => Some(MonitorEvent::Buffering),
+                PlayerState::Idle => None,
+            },
+            Some(last) => self.is_seek(last, &session).then_some(MonitorEvent::Seeked),
+        };
+
+        // A changed filter config or incognito can hide or reveal the session without any
+        // playback change
+        let filter = presence_filter(config, self.incognito.active, &session);
+        if event.is_some() || self.discord_failed || filter != self.presence_filter {
+            let artwork = match filter {
+                None => self.resolve_artwork(&session, token).await,
+                Some(_) => None,
+            };
+            let mut discord = self.discord_client.lock().await;
+            // Ensure connected
+            if !discord.is_connected() {
+                let _ = discord.connect(); // Try connect, ignore error for now log internally
+            }
+            let result = match filter {
+                None => discord.update_presence(&session, artwork.clone()),
+                Some(FilterAction::Generic) => {
+                    discord.update_presence(&generic_session(&session), None)
+                }
+                Some(FilterAction::Hide) => discord.clear_presence(),
+            };
+            self.presence_filter = filter;
+            self.shown_artwork = artwork;
+            match result {
+                Ok(()) => self.discord_failed = false,
+                Err(e) => {
+                    if !self.discord_failed {
+                        events.push(
+                            MonitorEvent::DiscordDisconnected {
+                                error: e.to_string(),
+                                session: Some(session.clone()),
                             }
-                        }
+                            .filtered(filter),
+                        );
                     }
-                } else {
-                    Ok("No active session".to_string())
+                    self.discord_failed = true;
                 }
             }
         }
+
+        if let Some(event) = event {
+            events.push(event(session.clone()).filtered(filter));
+        }
+        self.last_session = Some(session);
+        self.last_update_time = Some(Instant::now());
+        Ok(events)
     }
 
-    pub async fn get_servers(&self, config: &AppConfig) -> AppResult<Vec<PlexServer>> {
-        if let Some(token) = &config.auth_token {
-            self.plex_client.get_servers(token).await
-        } else {
-            Err(AppError::Auth("Not authenticated".into()))
+    /// Artwork for the presence, from the server the session plays on.
+    /// Failures only cost the image, the presence is still updated.
+    async fn resolve_artwork(&self, session: &Session, auth_token: &str) -> Option<String> {
+        let monitor = self
+            .servers
+            .iter()
+            .find(|m| m.session.as_ref().is_some_and(|s| s.is_same_media(session)))?;
+        match self
+            .artwork
+            .resolve(session, &monitor.server, auth_token)
+            .await
+        {
+            Ok(artwork) => artwork,
+            Err(e) => {
+                log::warn!(
+                    "Failed to resolve artwork for '{}': {}",
+                    session.media_title,
+                    e
+                );
+                None
+            }
         }
     }
 
-    pub fn get_last_session(&self) -> Option<&Session> {
+    /// Session currently shown on Discord, kept through the idle debounce.
+    pub fn current_session(&self) -> Option<&Session> {
         self.last_session.as_ref()
     }
+
+    /// How the privacy filters or incognito changed the presence of the current session.
+    pub fn presence_filter(&self) -> Option<FilterAction> {
+        self.presence_filter
+    }
+
+    /// Artwork URL or asset key shown with the current session, if any.
+    pub fn artwork(&self) -> Option<&str> {
+        self.shown_artwork.as_deref()
+    }
+
+    pub fn is_incognito(&self) -> bool {
+        self.incognito.active
+    }
+
+    pub async fn is_discord_connected(&self) -> bool {
+        !self.discord_failed && self.discord_client.lock().await.is_connected()
+    }
+
+    pub fn unreachable_servers(&self) -> Vec<String> {
+        self.servers
+            .iter()
+            .filter(|m| m.unreachable)
+            .map(|m| m.server.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::privacy::FilterMode;
+    use crate::domain::traits::{MockArtworkResolver, MockDiscordClient, MockPlexClient};
+
+    fn monitor() -> ServerMonitor {
+        ServerMonitor::new(PlexServer {
+            name: "Home".to_string(),
+            address: "192.168.1.10".to_string(),
+            port: 32400,
+            owned: true,
+            uri: None,
+            machine_identifier: None,
+            connections: Vec::new(),
+        })
+    }
+
+    fn session(key: &str, media_type: MediaType, state: PlayerState, player: &str) -> Session {
+        let mut session = Session::for_test(media_type, &format!("Item {}", key));
+        session.session_key = Some(key.to_string());
+        session.rating_key = Some(format!("10{}", key));
+        session.player_state = state;
+        session.player = Some(PlayerInfo {
+            title: Some(player.to_string()),
+            ..PlayerInfo::default()
+        });
+        session
+    }
+
+    fn playing(key: &str) -> Session {
+        session(key, MediaType::Movie, PlayerState::Playing, "TV")
+    }
+
+    /// Selects among `sessions` and tracks the result, like a refresh does.
+    fn poll(
+        monitor: &mut ServerMonitor,
+        sessions: Vec<Session>,
+        selection: &SessionSelection,
+    ) -> Option<String> {
+        let selected = monitor.select(sessions, selection);
+        monitor.set_session(selected);
+        let key = monitor.session.as_ref().and_then(|s| s.session_key.clone());
+        // Later polls happen later, without sleeping in the test
+        for seen in monitor.first_seen.values_mut() {
+            *seen -= Duration::from_secs(1);
+        }
+        key
+    }
+
+    #[test]
+    fn most_recently_started_wins_by_default() {
+        let selection = SessionSelection::default();
+        let mut monitor = monitor();
+
+        assert_eq!(
+            poll(&mut monitor, vec![playing("5")], &selection).as_deref(),
+            Some("5")
+        );
+        // Started later although its session key is lower
+        assert_eq!(
+            poll(&mut monitor, vec![playing("5"), playing("3")], &selection).as_deref(),
+            Some("3")
+        );
+    }
+
+    #[test]
+    fn ties_fall_back_to_the_higher_session_key() {
+        let selection = SessionSelection::default();
+        let mut monitor = monitor();
+
+        assert_eq!(
+            poll(
+                &mut monitor,
+                vec![playing("9"), playing("12"), playing("10")],
+                &selection
+            )
+            .as_deref(),
+            Some("12")
+        );
+    }
+
+    #[test]
+    fn playing_beats_paused_and_idle_is_ignored() {
+        let selection = SessionSelection::default();
+        let mut monitor = monitor();
+        let sessions = vec![
+            playing("1"),
+            session("2", MediaType::Movie, PlayerState::Paused, "TV"),
+            session("3", MediaType::Movie, PlayerState::Idle, "TV"),
+        ];
+
+        assert_eq!(
+            poll(&mut monitor, sessions, &selection).as_deref(),
+            Some("1")
+        );
+        assert!(!monitor.first_seen.contains_key("3"));
+        assert_eq!(
+            poll(
+                &mut monitor,
+                vec![session("3", MediaType::Movie, PlayerState::Idle, "TV")],
+                &selection
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn sticky_keeps_the_shown_session_while_paused() {
+        let selection = SessionSelection {
+            policy: SessionPolicy::Sticky,
+            ..SessionSelection::default()
+        };
+        let mut monitor = monitor();
+
+        assert_eq!(
+            poll(&mut monitor, vec![playing("1")], &selection).as_deref(),
+            Some("1")
+        );
+        let paused = session("1", MediaType::Movie, PlayerState::Paused, "TV");
+        assert_eq!(
+            poll(&mut monitor, vec![paused.clone(), playing("2")], &selection).as_deref(),
+            Some("1")
+        );
+
+        // Once it's gone the newest one takes over and is kept in turn
+        assert_eq!(
+            poll(&mut monitor, vec![playing("2")], &selection).as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            poll(&mut monitor, vec![playing("2"), playing("3")], &selection).as_deref(),
+            Some("2")
+        );
+    }
+
+    #[test]
+    fn device_order_ranks_listed_devices_first() {
+        let selection = SessionSelection {
+            policy: SessionPolicy::DeviceOrder,
+            device_order: vec!["living room tv".to_string(), "Plexamp".to_string()],
+            ..SessionSelection::default()
+        };
+        let mut monitor = monitor();
+        let tv = session(
+            "1",
+            MediaType::Movie,
+            PlayerState::Playing,
+            "Living Room TV",
+        );
+        let mut plexamp = session("2", MediaType::Track, PlayerState::Playing, "Phone");
+        plexamp.player.as_mut().unwrap().product = Some("Plexamp".to_string());
+        let laptop = session("3", MediaType::Movie, PlayerState::Playing, "Laptop");
+
+        assert_eq!(
+            poll(
+                &mut monitor,
+                vec![laptop.clone(), plexamp.clone()],
+                &selection
+            )
+            .as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            poll(
+                &mut monitor,
+                vec![laptop.clone(), plexamp.clone(), tv],
+                &selection
+            )
+            .as_deref(),
+            Some("1")
+        );
+        // Unlisted devices only win when nothing listed plays
+        assert_eq!(
+            poll(&mut monitor, vec![laptop], &selection).as_deref(),
+            Some("3")
+        );
+    }
+
+    #[test]
+    fn prefer_video_puts_music_last() {
+        let mut selection = SessionSelection::default();
+        let movie = playing("1");
+        let track = session("2", MediaType::Track, PlayerState::Playing, "Phone");
+
+        let mut monitor = monitor();
+        assert_eq!(
+            poll(&mut monitor, vec![movie.clone(), track.clone()], &selection).as_deref(),
+            Some("2")
+        );
+
+        selection.prefer_video = true;
+        let mut monitor = self::monitor();
+        assert_eq!(
+            poll(&mut monitor, vec![movie, track.clone()], &selection).as_deref(),
+            Some("1")
+        );
+        // Music still shows when nothing else plays
+        assert_eq!(
+            poll(&mut monitor, vec![track], &selection).as_deref(),
+            Some("2")
+        );
+    }
+
+    #[test]
+    fn identifies_players_by_machine_identifier() {
+        let living_room = PlayerInfo {
+            title: Some("Living Room TV".to_string()),
+            product: Some("Plex for Android (TV)".to_string()),
+            machine_identifier: Some("a7b3c9d1".to_string()),
+            ..PlayerInfo::default()
+        };
+        let players = HashMap::from([("a7b3c9d1".to_string(), living_room.clone())]);
+        let only_id = |id: &str| PlayerInfo {
+            machine_identifier: Some(id.to_string()),
+            ..PlayerInfo::default()
+        };
+
+        assert_eq!(
+            identify_player(Some(only_id("a7b3c9d1")), &players),
+            Some(living_room)
+        );
+        assert_eq!(
+            identify_player(Some(only_id("unknown")), &players),
+            Some(only_id("unknown"))
+        );
+        assert_eq!(identify_player(None, &players), None);
+    }
+
+    fn service(plex_client: MockPlexClient) -> MonitorService {
+        MonitorService::new(
+            Arc::new(plex_client),
+            Arc::new(Mutex::new(MockDiscordClient::new())),
+            Arc::new(MockArtworkResolver::new()),
+        )
+    }
+
+    fn matcher(devices: &[&str]) -> SessionMatcher {
+        SessionMatcher {
+            account_id: Some(12345678),
+            username: "alice".to_string(),
+            additional: AdditionalUsers::default(),
+            devices: DeviceFilter {
+                mode: FilterMode::Block,
+                values: devices.iter().map(|d| d.to_string()).collect(),
+            },
+        }
+    }
+
+    fn shared() -> ServerMonitor {
+        let mut monitor = monitor();
+        monitor.server.owned = false;
+        monitor
+    }
+
+    fn phone() -> PlayerInfo {
+        PlayerInfo {
+            title: Some("Phone".to_string()),
+            product: Some("Plexamp".to_string()),
+            machine_identifier: Some("a1".to_string()),
+            ..PlayerInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn looks_up_players_for_shared_servers() {
+        let mut plex_client = MockPlexClient::new();
+        plex_client
+            .expect_get_players()
+            .times(1)
+            .returning(|_| Ok(vec![phone()]));
+        let mut service = service(plex_client);
+        service.sync_servers(&[shared().server]);
+
+        service.refresh_players("token").await;
+        // Trusted for a while instead of being fetched on every refresh
+        service.refresh_players("token").await;
+
+        assert_eq!(
+            service.players.get("a1").and_then(|p| p.product.as_deref()),
+            Some("Plexamp")
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_player_lookup_without_shared_servers() {
+        // Any call would fail the expectation-less mock
+        let mut service = service(MockPlexClient::new());
+        service.sync_servers(&[monitor().server]);
+        service.refresh_players("token").await;
+    }
+
+    fn notification(session_key: &str, player: &str, state: PlayerState) -> PlexNotification {
+        PlexNotification::PlaySessionState(PlaySessionStateNotification {
+            session_key: session_key.to_string(),
+            key: format!("/library/metadata/10{}", session_key),
+            rating_key: Some(format!("10{}", session_key)),
+            client_identifier: Some(player.to_string()),
+            state,
+            view_offset: 60_000,
+        })
+    }
+
+    /// A shared server monitor following notifications, fed by the returned sender.
+    fn notified() -> (ServerMonitor, tokio::sync::mpsc::Sender<PlexNotification>) {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let mut monitor = shared();
+        monitor.notification_rx = Some(rx);
+        (monitor, tx)
+    }
+
+    /// Resolves every notification to the session it names, played on the notified player.
+    fn resolving_plex_client(times: usize) -> MockPlexClient {
+        let mut plex_client = MockPlexClient::new();
+        plex_client
+            .expect_get_session_from_notification()
+            .times(times)
+            .returning(|_, _, notification| {
+                let mut session = playing(&notification.session_key);
+                session.player = notification.player();
+                Ok(Some(session))
+            });
+        plex_client
+    }
+
+    async fn refresh(
+        monitor: &mut ServerMonitor,
+        plex_client: &MockPlexClient,
+        matcher: &SessionMatcher,
+    ) {
+        let players = HashMap::from([("a1".to_string(), phone())]);
+        monitor
+            .refresh_session(
+                plex_client,
+                "token",
+                matcher,
+                &SessionSelection::default(),
+                &players,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn notifications_start_update_and_stop_the_session() {
+        let (mut monitor, tx) = notified();
+        let plex_client = resolving_plex_client(1);
+        let matcher = matcher(&[]);
+
+        tx.send(notification("7", "a1", PlayerState::Playing))
+            .await
+            .unwrap();
+        refresh(&mut monitor, &plex_client, &matcher).await;
+        let session = monitor.session.clone().unwrap();
+        assert_eq!(session.session_key.as_deref(), Some("7"));
+        // Filled in from plex.tv
+        assert_eq!(
+            session.player.and_then(|p| p.title).as_deref(),
+            Some("Phone")
+        );
+
+        // Applied to the tracked session without fetching it again
+        tx.send(notification("7", "a1", PlayerState::Paused))
+            .await
+            .unwrap();
+        refresh(&mut monitor, &plex_client, &matcher).await;
+        let session = monitor.session.clone().unwrap();
+        assert_eq!(session.player_state, PlayerState::Paused);
+        assert_eq!(session.view_offset, 60_000);
+
+        // Stopped sessions come in as idle
+        tx.send(notification("7", "a1", PlayerState::Idle))
+            .await
+            .unwrap();
+        refresh(&mut monitor, &plex_client, &matcher).await;
+        assert!(monitor.session.is_none());
+    }
+
+    #[tokio::test]
+    async fn other_users_on_shared_servers_are_ignored() {
+        let (mut monitor, tx) = notified();
+        // Any lookup would fail the expectation-less mock
+        let plex_client = MockPlexClient::new();
+
+        // A player that isn't registered with the account
+        tx.send(notification("8", "b2", PlayerState::Playing))
+            .await
+            .unwrap();
+        refresh(&mut monitor, &plex_client, &matcher(&[])).await;
+
+        assert!(monitor.session.is_none());
+    }
+
+    #[tokio::test]
+    async fn device_filters_apply_to_notified_sessions() {
+        let (mut monitor, tx) = notified();
+        let plex_client = MockPlexClient::new();
+
+        tx.send(notification("7", "a1", PlayerState::Playing))
+            .await
+            .unwrap();
+        refresh(&mut monitor, &plex_client, &matcher(&["Plexamp"])).await;
+
+        assert!(monitor.session.is_none());
+    }
+
+    #[test]
+    fn timed_incognito_ends_once_at_its_time() {
+        let config = Incognito::Until { until: 1_000 };
+        let mut state = IncognitoState::default();
+
+        assert!(!state.update(&config, None, 999));
+        assert!(state.active);
+        assert!(state.update(&config, None, 1_000));
+        assert!(!state.active);
+        // Reported once, then stays off
+        assert!(!state.update(&config, None, 1_001));
+        assert!(!state.active);
+    }
+
+    #[test]
+    fn a_new_config_starts_over() {
+        let mut state = IncognitoState::default();
+        assert!(state.update(&Incognito::Until { until: 1_000 }, None, 2_000));
+
+        assert!(!state.update(&Incognito::Until { until: 3_000 }, None, 2_000));
+        assert!(state.active);
+        assert!(!state.update(&Incognito::On, None, 5_000));
+        assert!(state.active);
+        assert!(!state.update(&Incognito::Off, None, 5_000));
+        assert!(!state.active);
+    }
+
+    #[test]
+    fn until_media_ends_waits_for_the_item_to_stop() {
+        let item = playing("1");
+        let config = Incognito::until_media_ends(&item);
+        let mut state = IncognitoState::default();
+
+        // Not seen yet after a restart
+        assert!(!state.update(&config, None, 0));
+        assert!(state.active);
+        assert!(!state.update(&config, Some(&item), 0));
+        assert!(state.active);
+        assert!(state.update(&config, None, 0));
+        assert!(!state.active);
+        // The same item playing again doesn't bring it back
+        assert!(!state.update(&config, Some(&item), 0));
+        assert!(!state.active);
+    }
+
+    #[test]
+    fn until_media_ends_stops_when_another_item_plays() {
+        let config = Incognito::until_media_ends(&playing("1"));
+        let mut state = IncognitoState::default();
+
+        assert!(!state.update(&config, Some(&playing("1")), 0));
+        assert!(state.update(&config, Some(&playing("2")), 0));
+        assert!(!state.active);
+    }
 }
@@ -0,0 +1,263 @@
+use crate::application::monitor_service::MonitorService;
+use crate::domain::models::*;
+use crate::domain::privacy::FilterAction;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// How often servers without a notification socket are polled.
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// Events a slow subscriber can fall behind by before it starts missing some.
+const EVENT_CAPACITY: usize = 64;
+
+pub enum MonitorCommand {
+    /// Start monitoring with this config, or pick up changes to it
+    Reconfigure(Box<AppConfig>),
+    /// Stop monitoring and clear the presence, the task keeps running
+    Disconnect,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonitorStatus {
+    Stopped,
+    Monitoring,
+    /// Plex rejected the token, monitoring stopped until reconfigured with a new one
+    Unauthorized(String),
+}
+
+/// Snapshot published after every update, for consumers that only need the latest state.
+#[derive(Debug, Clone)]
+pub struct MonitorState {
+    pub status: MonitorStatus,
+    pub session: Option<Session>,
+    /// Set when the privacy filters or incognito keep `session` off Discord
+    pub presence_filter: Option<FilterAction>,
+    /// Artwork URL or asset key shown with `session` on Discord
+    pub artwork: Option<String>,
+    /// Discord updates are paused by `AppConfig::incognito`
+    pub incognito: bool,
+    pub discord_connected: bool,
+    pub unreachable_servers: Vec<String>,
+    pub updated_at: Instant,
+}
+
+impl MonitorState {
+    fn stopped() -> Self {
+        Self {
+            status: MonitorStatus::Stopped,
+            session: None,
+            presence_filter: None,
+            artwork: None,
+            incognito: false,
+            discord_connected: false,
+            unreachable_servers: Vec::new(),
+            updated_at: Instant::now(),
+        }
+    }
+}
+
+/// Handle to the long-running monitor task. Dropping it stops the task.
+pub struct MonitorHandle {
+    commands: mpsc::UnboundedSender<MonitorCommand>,
+    state: watch::Receiver<MonitorState>,
+    events: broadcast::Sender<FilteredEvent>,
+    task: JoinHandle<()>,
+}
+
+impl MonitorHandle {
+    pub fn spawn(service: MonitorService, runtime: &tokio::runtime::Handle) -> Self {
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        let (state_tx, state) = watch::channel(MonitorState::stopped());
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let task = runtime.spawn(run(service, command_rx, state_tx, events.clone()));
+        Self {
+            commands,
+            state,
+            events,
+            task,
+        }
+    }
+
+    pub fn reconfigure(&self, config: AppConfig) {
+        let _ = self
+            .commands
+            .send(MonitorCommand::Reconfigure(Box::new(config)));
+    }
+
+    pub fn disconnect(&self) {
+        let _ = self.commands.send(MonitorCommand::Disconnect);
+    }
+
+    /// Receiver of the state published after every update.
+    pub fn subscribe_state(&self) -> watch::Receiver<MonitorState> {
+        self.state.clone()
+    }
+
+    /// Receiver of every event from now on.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<FilteredEvent> {
+        self.events.subscribe()
+    }
+
+    /// Clears the presence and waits for the task to finish.
+    pub async fn shutdown(self) {
+        let _ = self.commands.send(MonitorCommand::Shutdown);
+        let _ = self.task.await;
+    }
+}
+
+async fn run(
+    mut service: MonitorService,
+    mut commands: mpsc::UnboundedReceiver<MonitorCommand>,
+    state: watch::Sender<MonitorState>,
+    events: broadcast::Sender<FilteredEvent>,
+) {
+    let mut config: Option<AppConfig> = None;
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let publish_events = |new_events: Vec<FilteredEvent>| {
+        for event in new_events {
+            // No subscribers is fine
+            let _ = events.send(event);
+        }
+    };
+
+    loop {
+        // Each branch finishes its network round trip before the next one starts,
+        // so a slow server delays the next tick instead of piling up requests.
+        let result = tokio::select! {
+            command = commands.recv() => {
+                match command {
+                    Some(MonitorCommand::Reconfigure(new_config)) => {
+                        config = Some(*new_config);
+                        interval.reset_immediately();
+                    }
+                    Some(MonitorCommand::Disconnect) => {
+                        config = None;
+                        publish_events(service.clear_state().await);
+                        state.send_replace(MonitorState::stopped());
+                    }
+                    Some(MonitorCommand::Shutdown) | None => break,
+                }
+                continue;
+            }
+            _ = interval.tick(), if config.is_some() => {
+                service.update(config.as_ref().unwrap()).await
+            }
+            _ = service.wait_for_notification(), if config.is_some() => {
+                service.handle_notifications(config.as_ref().unwrap()).await
+            }
+        };
+
+        let status = match result {
+            Ok(new_events) => {
+                publish_events(new_events);
+                MonitorStatus::Monitoring
+            }
+            Err(AppError::Unauthorized(e)) => {
+                // Nothing to do until a new login arrives through `Reconfigure`
+                config = None;
+                publish_events(service.clear_state().await);
+                MonitorStatus::Unauthorized(e)
+            }
+            Err(e) => {
+                log::warn!("Monitor update failed: {}", e);
+                MonitorStatus::Monitoring
+            }
+        };
+        state.send_replace(MonitorState {
+            status,
+            session: service.current_session().cloned(),
+            presence_filter: service.presence_filter(),
+            artwork: service.artwork().map(str::to_string),
+            incognito: service.is_incognito(),
+            discord_connected: service.is_discord_connected().await,
+            unreachable_servers: service.unreachable_servers(),
+            updated_at: Instant::now(),
+        });
+    }
+
+    publish_events(service.clear_state().await);
+    state.send_replace(MonitorState::stopped());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::traits::{MockArtworkResolver, MockDiscordClient, MockPlexClient};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn discord() -> MockDiscordClient {
+        let mut discord = MockDiscordClient::new();
+        discord.expect_is_connected().return_const(true);
+        discord.expect_update_presence().returning(|_, _| Ok(()));
+        discord.expect_clear_presence().returning(|| Ok(()));
+        discord
+    }
+
+    fn config() -> AppConfig {
+        AppConfig {
+            auth_token: Some("token".to_string()),
+            username: Some("alice".to_string()),
+            servers: vec![PlexServer {
+                name: "Home".to_string(),
+                address: "192.168.1.10".to_string(),
+                port: 32400,
+                owned: true,
+                uri: None,
+                machine_identifier: None,
+                connections: Vec::new(),
+            }],
+            ..AppConfig::default()
+        }
+    }
+
+    async fn next_event(events: &mut broadcast::Receiver<FilteredEvent>) -> MonitorEvent {
+        tokio::time::timeout(Duration::from_secs(30), events.recv())
+            .await
+            .expect("no monitor event")
+            .unwrap()
+            .event
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_rejected_token_ends_the_shown_session() {
+        let mut plex = MockPlexClient::new();
+        let mut polls = 0;
+        plex.expect_get_sessions().returning(move |_, _, _| {
+            polls += 1;
+            match polls {
+                1 => Ok(vec![Session::for_test(MediaType::Movie, "Heat")]),
+                _ => Err(AppError::Unauthorized("401".into())),
+            }
+        });
+        let mut artwork = MockArtworkResolver::new();
+        artwork.expect_resolve().returning(|_, _, _| Ok(None));
+        let service = MonitorService::new(
+            Arc::new(plex),
+            Arc::new(Mutex::new(discord())),
+            Arc::new(artwork),
+        );
+        let monitor = MonitorHandle::spawn(service, &tokio::runtime::Handle::current());
+        let mut events = monitor.subscribe_events();
+        let mut state = monitor.subscribe_state();
+
+        monitor.reconfigure(config());
+
+        assert!(matches!(
+            next_event(&mut events).await,
+            MonitorEvent::SessionStarted(_)
+        ));
+        match next_event(&mut events).await {
+            MonitorEvent::Ended(session) => assert_eq!(session.media_title, "Heat"),
+            other => panic!("expected the session to end, got {:?}", other),
+        }
+        state
+            .wait_for(|s| matches!(s.status, MonitorStatus::Unauthorized(_)))
+            .await
+            .unwrap();
+        assert!(state.borrow().session.is_none());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::domain::models::*;
+use crate::domain::traits::PlexClient;
+use futures_util::future::join_all;
+use std::sync::Arc;
+
+/// Probes every known connection concurrently and returns the server using the
+/// best reachable one: local first, then remote, then relay.
+pub async fn probe_connections(
+    plex_client: &dyn PlexClient,
+    server: &PlexServer,
+    token: &str,
+) -> AppResult<PlexServer> {
+    // Manually added servers only have the one address
+    if server.connections.is_empty() {
+        plex_client
+            .check_connection(&server.base_url(), token, server.owned)
+            .await?;
+        return Ok(server.clone());
+    }
+
+    let results = join_all(
+        server
+            .connections
+            .iter()
+            .map(|c| plex_client.check_connection(&c.uri, token, server.owned)),
+    )
+    .await;
+
+    let mut last_error = None;
+    let mut best: Option<&ServerConnection> = None;
+    for (connection, result) in server.connections.iter().zip(results) {
+        match result {
+            Ok(()) => {
+                // Plex lists connections in its own preferred order, keep it within a tier
+                if best.is_none_or(|b| connection.preference() < b.preference()) {
+                    best = Some(connection);
+                }
+            }
+            Err(e) => {
+                log::debug!("Connection {} unreachable: {}", connection.uri, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match best {
+        Some(connection) => Ok(server.with_connection(connection)),
+        None => Err(last_error.unwrap_or_else(|| {
+            AppError::PlexApi(format!("No reachable connection for {}", server.name))
+        })),
+    }
+}
+
+/// Server discovery and verification, used while picking servers to monitor.
+pub struct ServerService {
+    plex_client: Arc<dyn PlexClient>,
+}
+
+impl ServerService {
+    pub fn new(plex_client: Arc<dyn PlexClient>) -> Self {
+        Self { plex_client }
+    }
+
+    /// Picks the best reachable connection and checks the server works with the
+    /// saved login before it is added. Returns the server using that connection.
+    pub async fn verify_server(
+        &self,
+        server: &PlexServer,
+        config: &AppConfig,
+    ) -> AppResult<PlexServer> {
+        let (Some(token), Some(matcher)) = (&config.auth_token, config.session_matcher()) else {
+            return Err(AppError::Auth("Not authenticated".into()));
+        };
+
+        // Connections are checked against /identity with the token, which is all
+        // non-owners can verify: they can't list sessions
+        let server = probe_connections(self.plex_client.as_ref(), server, token).await?;
+
+        if server.owned {
+            self.plex_client
+                .get_sessions(&server, token, &matcher)
+                .await?;
+        }
+
+        Ok(server)
+    }
+
+    pub async fn get_servers(&self, config: &AppConfig) -> AppResult<Vec<PlexServer>> {
+        if let Some(token) = &config.auth_token {
+            self.plex_client.get_servers(token).await
+        } else {
+            Err(AppError::Auth("Not authenticated".into()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::traits::MockPlexClient;
+
+    fn server(owned: bool) -> PlexServer {
+        PlexServer {
+            name: "Home".to_string(),
+            address: "192.168.1.10".to_string(),
+            port: 32400,
+            owned,
+            uri: None,
+            machine_identifier: None,
+            connections: Vec::new(),
+        }
+    }
+
+    fn config() -> AppConfig {
+        AppConfig {
+            auth_token: Some("token".to_string()),
+            username: Some("alice".to_string()),
+            ..AppConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_servers_are_verified_by_their_identity() {
+        let mut plex = MockPlexClient::new();
+        plex.expect_check_connection()
+            .withf(|_, _, owned| !owned)
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        // No session listing or notification socket for servers we don't own
+        let service = ServerService::new(Arc::new(plex));
+
+        assert!(service
+            .verify_server(&server(false), &config())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn owned_servers_must_list_sessions() {
+        let mut plex = MockPlexClient::new();
+        plex.expect_check_connection().returning(|_, _, _| Ok(()));
+        plex.expect_get_sessions()
+            .times(1)
+            .returning(|_, _, _| Err(AppError::Unauthorized("401".into())));
+        let service = ServerService::new(Arc::new(plex));
+
+        let result = service.verify_server(&server(true), &config()).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    fn connection(address: &str, local: bool, relay: bool) -> ServerConnection {
+        ServerConnection {
+            protocol: "https".to_string(),
+            address: address.to_string(),
+            port: 32400,
+            uri: format!("https://{}:32400", address),
+            local,
+            relay,
+        }
+    }
+
+    fn with_connections(connections: Vec<ServerConnection>) -> PlexServer {
+        PlexServer {
+            connections,
+            ..server(true)
+        }
+    }
+
+    /// Reachable only at the addresses in `reachable`.
+    fn reaching(reachable: &'static [&'static str]) -> MockPlexClient {
+        let mut plex = MockPlexClient::new();
+        plex.expect_check_connection().returning(move |uri, _, _| {
+            if reachable.iter().any(|address| uri.contains(address)) {
+                Ok(())
+            } else {
+                Err(AppError::PlexApi(format!("{} unreachable", uri)))
+            }
+        });
+        plex
+    }
+
+    #[tokio::test]
+    async fn prefers_local_then_remote_then_relay() {
+        let server = with_connections(vec![
+            connection("relay.plex.direct", false, true),
+            connection("203.0.113.5", false, false),
+            connection("10.0.0.2", true, false),
+            connection("10.0.0.3", true, false),
+        ]);
+
+        let plex = reaching(&["relay", "203.0.113.5", "10.0.0.2", "10.0.0.3"]);
+        let probed = probe_connections(&plex, &server, "token").await.unwrap();
+        // Plex's own order decides within a tier
+        assert_eq!(probed.address, "10.0.0.2");
+        assert_eq!(probed.uri.as_deref(), Some("https://10.0.0.2:32400"));
+
+        let plex = reaching(&["relay", "203.0.113.5"]);
+        let probed = probe_connections(&plex, &server, "token").await.unwrap();
+        assert_eq!(probed.address, "203.0.113.5");
+
+        let plex = reaching(&["relay"]);
+        let probed = probe_connections(&plex, &server, "token").await.unwrap();
+        assert_eq!(probed.address, "relay.plex.direct");
+    }
+
+    #[tokio::test]
+    async fn fails_when_no_connection_is_reachable() {
+        let server = with_connections(vec![
+            connection("10.0.0.2", true, false),
+            connection("203.0.113.5", false, false),
+        ]);
+
+        let result = probe_connections(&reaching(&[]), &server, "token").await;
+        assert!(matches!(result, Err(AppError::PlexApi(_))));
+    }
+
+    #[tokio::test]
+    async fn manually_added_servers_check_their_address() {
+        let mut plex = MockPlexClient::new();
+        plex.expect_check_connection()
+            .withf(|uri, _, _| uri == "http://192.168.1.10:32400")
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let probed = probe_connections(&plex, &server(true), "token")
+            .await
+            .unwrap();
+        assert_eq!(probed, server(true));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::domain::models::{MediaType, WatchHistoryEntry};
+use jiff::civil::Date;
+use jiff::tz::TimeZone;
+use jiff::{Span, Timestamp};
+use std::collections::HashMap;
+
+/// Days shown in the daily chart, ending today.
+const DAYS: i64 = 14;
+/// Weeks shown in the weekly chart, ending with the current one.
+const WEEKS: i64 = 8;
+/// Entries in each top list.
+const TOP: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct PeriodTotal {
+    /// First day of the period in local time
+    pub start: Date,
+    pub watched_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TitleTotal {
+    pub title: String,
+    pub watched_ms: u64,
+    pub plays: usize,
+}
+
+/// Summary of the local watch history.
+#[derive(Debug, Clone, Default)]
+pub struct WatchStatistics {
+    pub viewings: usize,
+    pub finished: usize,
+    pub total_watched_ms: u64,
+    /// Oldest first, days without viewings included
+    pub per_day: Vec<PeriodTotal>,
+    /// Oldest first, weeks start on Monday
+    pub per_week: Vec<PeriodTotal>,
+    pub top_shows: Vec<TitleTotal>,
+    pub top_artists: Vec<TitleTotal>,
+    pub top_movies: Vec<TitleTotal>,
+    /// Largest first, types that were never watched are left out
+    pub by_media_type: Vec<(MediaType, u64)>,
+}
+
+impl WatchStatistics {
+    /// Groups `entries` by the local day they started on, relative to `today`.
+    pub fn from_entries(entries: &[WatchHistoryEntry], today: Date, tz: &TimeZone) -> Self {
+        let first_day = today - Span::new().days(DAYS - 1);
+        let this_week = week_start(today);
+        let first_week = this_week - Span::new().weeks(WEEKS - 1);
+
+        let mut per_day: Vec<PeriodTotal> = (0..DAYS)
+            .map(|n| PeriodTotal {
+                start: first_day + Span::new().days(n),
+                watched_ms: 0,
+            })
+            .collect();
+        let mut per_week: Vec<PeriodTotal> = (0..WEEKS)
+            .map(|n| PeriodTotal {
+                start: first_week + Span::new().weeks(n),
+                watched_ms: 0,
+            })
+            .collect();
+        let mut shows = HashMap::new();
+        let mut artists = HashMap::new();
+        let mut movies = HashMap::new();
+        // Episode, Movie, Track, everything else
+        let mut by_type = [0u64; 4];
+
+        let mut stats = WatchStatistics::default();
+        for entry in entries {
+            stats.viewings += 1;
+            stats.finished += usize::from(entry.finished);
+            stats.total_watched_ms += entry.watched_ms;
+
+            if let Some(day) = local_date(entry.started_at, tz) {
+                if day >= first_day && day <= today {
+                    let idx = (day - first_day).get_days();
+                    per_day[idx as usize].watched_ms += entry.watched_ms;
+                }
+                let week = week_start(day);
+                if week >= first_week && week <= this_week {
+                    let idx = (week - first_week).get_days() / 7;
+                    per_week[idx as usize].watched_ms += entry.watched_ms;
+                }
+            }
+
+            let session = &entry.session;
+            let (totals, title, slot) = match session.media_type {
+                MediaType::Episode => (
+                    Some(&mut shows),
+                    session.media_grandparent_title.as_ref(),
+                    0,
+                ),
+                MediaType::Movie => (Some(&mut movies), Some(&session.media_title), 1),
+                MediaType::Track => (
+                    Some(&mut artists),
+                    session.media_grandparent_title.as_ref(),
+                    2,
+                ),
+                MediaType::Unknown | MediaType::Idle => (None, None, 3),
+            };
+            by_type[slot] += entry.watched_ms;
+            if let (Some(totals), Some(title)) = (totals, title) {
+                let total = totals.entry(title.clone()).or_insert((0, 0));
+                total.0 += entry.watched_ms;
+                total.1 += 1;
+            }
+        }
+
+        stats.per_day = per_day;
+        stats.per_week = per_week;
+        stats.top_shows = top(shows);
+        stats.top_artists = top(artists);
+        stats.top_movies = top(movies);
+
+        let types = [
+            MediaType::Episode,
+            MediaType::Movie,
+            MediaType::Track,
+            MediaType::Unknown,
+        ];
+        stats.by_media_type = types
+            .into_iter()
+            .zip(by_type)
+            .filter(|(_, ms)| *ms > 0)
+            .collect();
+        stats
+            .by_media_type
+            .sort_by_key(|(_, ms)| std::cmp::Reverse(*ms));
+        stats
+    }
+}
+
+fn local_date(unix_secs: u64, tz: &TimeZone) -> Option<Date> {
+    let timestamp = Timestamp::from_second(i64::try_from(unix_secs).ok()?).ok()?;
+    Some(timestamp.to_zoned(tz.clone()).date())
+}
+
+/// Monday of the week `date` is in.
+fn week_start(date: Date) -> Date {
+    date - Span::new().days(i64::from(date.weekday().to_monday_zero_offset()))
+}
+
+fn top(totals: HashMap<String, (u64, usize)>) -> Vec<TitleTotal> {
+    let mut list: Vec<TitleTotal> = totals
+        .into_iter()
+        .map(|(title, (watched_ms, plays))| TitleTotal {
+            title,
+            watched_ms,
+            plays,
+        })
+        .collect();
+    // Ties broken by title so the order doesn't change between refreshes
+    list.sort_by(|a, b| {
+        b.watched_ms
+            .cmp(&a.watched_ms)
+            .then_with(|| a.title.cmp(&b.title))
+    });
+    list.truncate(TOP);
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::Session;
+    use jiff::civil::date;
+
+    fn at(date: Date, hour: i8, minute: i8) -> u64 {
+        date.at(hour, minute, 0, 0)
+            .to_zoned(TimeZone::UTC)
+            .unwrap()
+            .timestamp()
+            .as_second() as u64
+    }
+
+    fn entry(
+        media_type: MediaType,
+        title: &str,
+        started_at: u64,
+        minutes: u64,
+    ) -> WatchHistoryEntry {
+        let mut session = Session::for_test(media_type.clone(), title);
+        if media_type != MediaType::Movie {
+            session.media_grandparent_title = Some(title.to_string());
+            session.media_title = "Item".to_string();
+        }
+        WatchHistoryEntry {
+            started_at,
+            ended_at: started_at + minutes * 60,
+            watched_ms: minutes * 60_000,
+            finished: minutes >= 30,
+            session,
+        }
+    }
+
+    // A Saturday
+    const TODAY: Date = date(2026, 1, 24);
+
+    fn day(stats: &WatchStatistics, date: Date) -> u64 {
+        stats
+            .per_day
+            .iter()
+            .find(|d| d.start == date)
+            .map(|d| d.watched_ms)
+            .unwrap()
+    }
+
+    #[test]
+    fn buckets_by_day_ending_today() {
+        let entries = [
+            entry(MediaType::Movie, "The Matrix", at(TODAY, 20, 0), 120),
+            entry(MediaType::Movie, "Heat", at(date(2026, 1, 23), 9, 0), 60),
+            // Before the chart, only in the totals
+            entry(MediaType::Movie, "Alien", at(date(2026, 1, 10), 21, 0), 90),
+        ];
+
+        let stats = WatchStatistics::from_entries(&entries, TODAY, &TimeZone::UTC);
+
+        assert_eq!(stats.viewings, 3);
+        assert_eq!(stats.finished, 3);
+        assert_eq!(stats.total_watched_ms, 270 * 60_000);
+        assert_eq!(stats.per_day.len(), 14);
+        assert_eq!(stats.per_day.first().unwrap().start, date(2026, 1, 11));
+        assert_eq!(stats.per_day.last().unwrap().start, TODAY);
+        assert_eq!(day(&stats, TODAY), 120 * 60_000);
+        assert_eq!(day(&stats, date(2026, 1, 23)), 60 * 60_000);
+        assert_eq!(
+            stats.per_day.iter().map(|d| d.watched_ms).sum::<u64>(),
+            180 * 60_000
+        );
+    }
+
+    #[test]
+    fn a_viewing_across_midnight_counts_for_the_day_it_started() {
+        let entries = [entry(
+            MediaType::Movie,
+            "Heat",
+            at(date(2026, 1, 23), 23, 30),
+            170,
+        )];
+
+        let stats = WatchStatistics::from_entries(&entries, TODAY, &TimeZone::UTC);
+
+        assert_eq!(day(&stats, date(2026, 1, 23)), 170 * 60_000);
+        assert_eq!(day(&stats, TODAY), 0);
+    }
+
+    #[test]
+    fn days_follow_the_local_time_zone() {
+        // 02:00 UTC on Saturday is still Friday evening in UTC-5
+        let entries = [entry(MediaType::Movie, "Heat", at(TODAY, 2, 0), 60)];
+        let tz = TimeZone::fixed(jiff::tz::offset(-5));
+
+        let stats = WatchStatistics::from_entries(&entries, TODAY, &tz);
+
+        assert_eq!(day(&stats, date(2026, 1, 23)), 60 * 60_000);
+        assert_eq!(day(&stats, TODAY), 0);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let entries = [
+            entry(MediaType::Movie, "Monday", at(date(2026, 1, 19), 0, 30), 60),
+            entry(
+                MediaType::Movie,
+                "Sunday",
+                at(date(2026, 1, 18), 23, 30),
+                30,
+            ),
+        ];
+
+        let stats = WatchStatistics::from_entries(&entries, TODAY, &TimeZone::UTC);
+
+        assert_eq!(stats.per_week.len(), 8);
+        let this_week = stats.per_week.last().unwrap();
+        let last_week = &stats.per_week[6];
+        assert_eq!(this_week.start, date(2026, 1, 19));
+        assert_eq!(this_week.watched_ms, 60 * 60_000);
+        assert_eq!(last_week.start, date(2026, 1, 12));
+        assert_eq!(last_week.watched_ms, 30 * 60_000);
+        assert_eq!(stats.per_week[0].start, date(2025, 12, 1));
+    }
+
+    #[test]
+    fn top_lists_group_by_show_artist_and_movie() {
+        let start = at(TODAY, 12, 0);
+        let mut entries = vec![
+            entry(MediaType::Episode, "Breaking Bad", start, 45),
+            entry(MediaType::Episode, "Breaking Bad", start, 45),
+            entry(MediaType::Episode, "The Office", start, 60),
+            entry(MediaType::Track, "Queen", start, 5),
+            entry(MediaType::Unknown, "Home Videos", start, 10),
+        ];
+        // Ties are ordered by title, only the first five are kept
+        for title in ["F", "E", "D", "C", "B", "A"] {
+            entries.push(entry(MediaType::Movie, title, start, 100));
+        }
+
+        let stats = WatchStatistics::from_entries(&entries, TODAY, &TimeZone::UTC);
+
+        let shows: Vec<_> = stats
+            .top_shows
+            .iter()
+            .map(|t| (t.title.as_str(), t.watched_ms, t.plays))
+            .collect();
+        assert_eq!(
+            shows,
+            [
+                ("Breaking Bad", 90 * 60_000, 2),
+                ("The Office", 60 * 60_000, 1)
+            ]
+        );
+        assert_eq!(stats.top_artists[0].title, "Queen");
+        let movies: Vec<_> = stats.top_movies.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(movies, ["A", "B", "C", "D", "E"]);
+        assert_eq!(
+            stats.by_media_type,
+            [
+                (MediaType::Movie, 600 * 60_000),
+                (MediaType::Episode, 150 * 60_000),
+                (MediaType::Unknown, 10 * 60_000),
+                (MediaType::Track, 5 * 60_000),
+            ]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::domain::models::{MediaType, Session};
+use serde::{Deserialize, Serialize};
+
+/// Discord shows at most this many buttons on an activity.
+pub const MAX_BUTTONS: usize = 2;
+
+/// Site a presence button can link to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkProvider {
+    Imdb,
+    Tmdb,
+    Tvdb,
+    /// The recording by its `mbid://` Guid, or a search by artist and title
+    #[serde(rename = "musicbrainz")]
+    MusicBrainz,
+    /// The item in Plex Web, only opens for people with access to the server
+    Plex,
+}
+
+impl LinkProvider {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LinkProvider::Imdb => "IMDb",
+            LinkProvider::Tmdb => "View on TMDB",
+            LinkProvider::Tvdb => "View on TVDB",
+            LinkProvider::MusicBrainz => "MusicBrainz",
+            LinkProvider::Plex => "Open in Plex",
+        }
+    }
+
+    /// Page for `session` on this site, if its metadata has the ID needed.
+    pub fn url(&self, session: &Session) -> Option<String> {
+        match self {
+            LinkProvider::Imdb => session
+                .guid("imdb")
+                .map(|id| format!("https://www.imdb.com/title/{}/", id)),
+            // TMDB episode pages need the show's ID, which episode metadata doesn't carry
+            LinkProvider::Tmdb => match session.media_type {
+                MediaType::Movie => session
+                    .guid("tmdb")
+                    .map(|id| format!("https://www.themoviedb.org/movie/{}", id)),
+                _ => None,
+            },
+            LinkProvider::Tvdb => {
+                let kind = match session.media_type {
+                    MediaType::Episode => "episode",
+                    MediaType::Movie => "movie",
+                    _ => return None,
+                };
+                session
+                    .guid("tvdb")
+                    .map(|id| format!("https://www.thetvdb.com/dereferrer/{}/{}", kind, id))
+            }
+            LinkProvider::MusicBrainz => {
+                if session.media_type != MediaType::Track {
+                    return None;
+                }
+                if let Some(id) = session.guid("mbid") {
+                    return Some(format!("https://musicbrainz.org/recording/{}", id));
+                }
+                let query = match &session.media_grandparent_title {
+                    Some(artist) => format!("{} {}", artist, session.media_title),
+                    None => session.media_title.clone(),
+                };
+                let query: String =
+                    url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+                Some(format!(
+                    "https://musicbrainz.org/search?type=recording&query={}",
+                    query
+                ))
+            }
+            LinkProvider::Plex => {
+                let server_id = session.server_id.as_ref()?;
+                let rating_key = session.rating_key.as_ref()?;
+                Some(format!(
+                    "https://app.plex.tv/desktop/#!/server/{}/details?key=%2Flibrary%2Fmetadata%2F{}",
+                    server_id, rating_key
+                ))
+            }
+        }
+    }
+}
+
+/// Providers tried in order for each media type. Ones without a link for the
+/// playing item are skipped, the first `MAX_BUTTONS` that have one are shown.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresenceButtons {
+    pub episode: Vec<LinkProvider>,
+    pub movie: Vec<LinkProvider>,
+    pub track: Vec<LinkProvider>,
+}
+
+impl Default for PresenceButtons {
+    fn default() -> Self {
+        Self {
+            episode: vec![LinkProvider::Imdb, LinkProvider::Tvdb],
+            movie: vec![LinkProvider::Imdb, LinkProvider::Tmdb],
+            track: vec![LinkProvider::MusicBrainz],
+        }
+    }
+}
+
+impl PresenceButtons {
+    /// Label and URL of each button to show for `session`.
+    pub fn for_session(&self, session: &Session) -> Vec<(&'static str, String)> {
+        let providers: &[LinkProvider] = match session.media_type {
+            MediaType::Episode => &self.episode,
+            MediaType::Movie => &self.movie,
+            MediaType::Track => &self.track,
+            MediaType::Unknown | MediaType::Idle => &[],
+        };
+        providers
+            .iter()
+            .filter_map(|provider| Some((provider.label(), provider.url(session)?)))
+            .take(MAX_BUTTONS)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_guids(media_type: MediaType, guids: &[&str]) -> Session {
+        let mut session = Session::for_test(media_type, "Heat");
+        session.guids = guids.iter().map(|g| g.to_string()).collect();
+        session
+    }
+
+    #[test]
+    fn movie_links() {
+        let movie = with_guids(
+            MediaType::Movie,
+            &["imdb://tt0113277", "tmdb://949", "tvdb://395"],
+        );
+
+        assert_eq!(
+            LinkProvider::Imdb.url(&movie).as_deref(),
+            Some("https://www.imdb.com/title/tt0113277/")
+        );
+        assert_eq!(
+            LinkProvider::Tmdb.url(&movie).as_deref(),
+            Some("https://www.themoviedb.org/movie/949")
+        );
+        assert_eq!(
+            LinkProvider::Tvdb.url(&movie).as_deref(),
+            Some("https://www.thetvdb.com/dereferrer/movie/395")
+        );
+        assert_eq!(LinkProvider::MusicBrainz.url(&movie), None);
+    }
+
+    #[test]
+    fn episode_links() {
+        let episode = with_guids(
+            MediaType::Episode,
+            &["imdb://tt0959621", "tmdb://62085", "tvdb://349232"],
+        );
+
+        assert_eq!(
+            LinkProvider::Imdb.url(&episode).as_deref(),
+            Some("https://www.imdb.com/title/tt0959621/")
+        );
+        assert_eq!(LinkProvider::Tmdb.url(&episode), None);
+        assert_eq!(
+            LinkProvider::Tvdb.url(&episode).as_deref(),
+            Some("https://www.thetvdb.com/dereferrer/episode/349232")
+        );
+    }
+
+    #[test]
+    fn missing_ids_have_no_link() {
+        let movie = with_guids(MediaType::Movie, &["imdb://"]);
+
+        assert_eq!(LinkProvider::Imdb.url(&movie), None);
+        assert_eq!(LinkProvider::Tmdb.url(&movie), None);
+        assert_eq!(
+            LinkProvider::Tvdb.url(&with_guids(MediaType::Track, &["tvdb://1"])),
+            None
+        );
+    }
+
+    #[test]
+    fn musicbrainz_links_the_recording_or_searches_for_it() {
+        let track = with_guids(
+            MediaType::Track,
+            &["mbid://b1a9c0e9-d987-4042-ae91-78d6a3267d69"],
+        );
+        assert_eq!(
+            LinkProvider::MusicBrainz.url(&track).as_deref(),
+            Some("https://musicbrainz.org/recording/b1a9c0e9-d987-4042-ae91-78d6a3267d69")
+        );
+
+        let mut track = Session::for_test(MediaType::Track, "Don't Stop Me Now");
+        track.media_grandparent_title = Some("Queen".to_string());
+        assert_eq!(
+            LinkProvider::MusicBrainz.url(&track).as_deref(),
+            Some("https://musicbrainz.org/search?type=recording&query=Queen+Don%27t+Stop+Me+Now")
+        );
+
+        track.media_grandparent_title = None;
+        track.media_title = "A&B".to_string();
+        assert_eq!(
+            LinkProvider::MusicBrainz.url(&track).as_deref(),
+            Some("https://musicbrainz.org/search?type=recording&query=A%26B")
+        );
+    }
+
+    #[test]
+    fn plex_links_need_the_server_and_item() {
+        let mut movie = Session::for_test(MediaType::Movie, "Heat");
+        assert_eq!(LinkProvider::Plex.url(&movie), None);
+
+        movie.server_id = Some("abc123".to_string());
+        assert_eq!(
+            LinkProvider::Plex.url(&movie).as_deref(),
+            Some("https://app.plex.tv/desktop/#!/server/abc123/details?key=%2Flibrary%2Fmetadata%2F100")
+        );
+    }
+
+    #[test]
+    fn buttons_skip_providers_without_a_link_and_stop_at_the_limit() {
+        let buttons = PresenceButtons {
+            movie: vec![
+                LinkProvider::Tvdb,
+                LinkProvider::Imdb,
+                LinkProvider::Plex,
+                LinkProvider::Tmdb,
+            ],
+            ..PresenceButtons::default()
+        };
+        let mut movie = with_guids(MediaType::Movie, &["imdb://tt0113277", "tmdb://949"]);
+        movie.server_id = Some("abc123".to_string());
+
+        let shown = buttons.for_session(&movie);
+
+        assert_eq!(shown.len(), MAX_BUTTONS);
+        assert_eq!(shown[0].0, "IMDb");
+        assert_eq!(shown[1].0, "Open in Plex");
+    }
+
+    #[test]
+    fn no_buttons_for_other_media() {
+        let session = with_guids(MediaType::Unknown, &["imdb://tt0113277"]);
+        assert!(PresenceButtons::default().for_session(&session).is_empty());
+    }
+}
@@ -1,2 +1,5 @@
+pub mod links;
 pub mod models;
+pub mod privacy;
+pub mod template;
 pub mod traits;
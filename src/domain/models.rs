@@ -1,4 +1,8 @@
+use crate::domain::links::PresenceButtons;
+use crate::domain::privacy::{generic_session, FilterAction, FilterMode, PrivacyFilters};
+use crate::domain::template::{PresenceTemplate, PresenceTemplates};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,37 +15,290 @@ pub enum AppError {
     Config(String),
     #[error("Authentication error: {0}")]
     Auth(String),
+    #[error("Plex login expired or was revoked: {0}")]
+    Unauthorized(String),
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
-    #[error("Unknown error: {0}")]
-    Unknown(String),
 }
 
 pub type AppResult<T> = Result<T, AppError>;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct AppConfig {
+    /// Layout version of `config.json`, older files are migrated on load
+    #[serde(default)]
+    pub version: u32,
     pub auth_token: Option<String>,
     pub username: Option<String>,
+    /// plex.tv account ID of `username`, missing in configs saved by older releases
+    #[serde(default)]
+    pub user_id: Option<u64>,
     pub client_id: String,
-    pub server_address: Option<String>,
-    pub server_port: Option<u16>,
-    pub server_name: Option<String>,
-    pub is_owned: Option<bool>,
+    /// Monitored servers, in priority order for `MultiServerPolicy::ServerOrder`
+    #[serde(default)]
+    pub servers: Vec<PlexServer>,
+    #[serde(default)]
+    pub multi_server_policy: MultiServerPolicy,
+    /// Which session is shown when the user plays on several players of one server
+    #[serde(default)]
+    pub session_selection: SessionSelection,
+    /// Other Plex users whose sessions are shown as if they were the signed-in account's
+    #[serde(default)]
+    pub additional_users: AdditionalUsers,
+    /// Players the presence is restricted to or never shown for
+    #[serde(default)]
+    pub devices: DeviceFilter,
+    #[serde(default)]
+    pub presence_templates: PresenceTemplates,
+    /// Link buttons shown under the presence, per media type
+    #[serde(default)]
+    pub presence_buttons: PresenceButtons,
+    /// How `auth_token` is protected in `config.json`
+    #[serde(default)]
+    pub token_encryption: TokenEncryption,
+    /// Sessions kept off Discord, evaluated before every presence update
+    #[serde(default)]
+    pub privacy: PrivacyFilters,
+    /// Presence paused while sessions are still tracked, kept across restarts
+    #[serde(default)]
+    pub incognito: Incognito,
+    /// Where the large image of the presence comes from
+    #[serde(default)]
+    pub artwork: ArtworkConfig,
+    #[serde(default)]
+    pub status_api: StatusApiConfig,
+    #[serde(default)]
+    pub now_playing: NowPlayingConfig,
+}
+
+/// Encryption applied to the saved auth token. The key never lives in `config.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenEncryption {
+    #[default]
+    None,
+    /// Random key in `token.key` next to the config, created on first save
+    KeyFile,
+    /// Key derived from the `PLEX_DISCORD_RPC_PASSPHRASE` environment variable
+    Passphrase,
+}
+
+/// Which server's session wins the single Discord presence when several are active.
+/// Playing or buffering sessions always win over paused ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MultiServerPolicy {
+    #[default]
+    MostRecentlyStarted,
+    ServerOrder,
+}
+
+/// Which of the user's concurrent sessions on a server is shown. Playing or buffering
+/// sessions win over paused ones, except for the kept session of `Sticky`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionPolicy {
+    /// Keep the shown session while it exists, whatever else starts
+    Sticky,
+    #[default]
+    MostRecentlyStarted,
+    /// The player first in `SessionSelection::device_order`
+    DeviceOrder,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SessionSelection {
+    pub policy: SessionPolicy,
+    /// Player titles, products, platforms, devices or machine identifiers, preferred first
+    pub device_order: Vec<String>,
+    /// Movies and episodes win over music, before the policy decides
+    pub prefer_video: bool,
+}
+
+/// Source of the presence's large image. Plex artwork URLs are never shown to Discord,
+/// they need the token and are often only reachable on the local network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ArtworkConfig {
+    /// No large image
+    #[default]
+    None,
+    /// An image uploaded to the Discord application, by asset key
+    Static { asset_key: String },
+    /// Posters from TMDB and album covers from the Cover Art Archive, found by Guid
+    External {
+        /// Needed for movie and show posters, music works without
+        #[serde(default)]
+        tmdb_api_key: Option<String>,
+    },
+    /// The Plex artwork is POSTed to this endpoint, which answers with a public URL
+    Upload {
+        endpoint: String,
+        /// Sent as the `Authorization` header
+        #[serde(default)]
+        authorization: Option<String>,
+    },
+}
+
+/// Opt-in HTTP API serving the monitor state, for overlays and dashboards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusApiConfig {
+    pub enabled: bool,
+    /// Loopback by default, anything else exposes the API to the network
+    pub bind: String,
+    pub port: u16,
+    /// Sent as `Access-Control-Allow-Origin`, needed for browser overlays on another origin
+    pub allowed_origin: Option<String>,
+    /// Serve the session even while incognito or a privacy filter keeps it off Discord
+    pub include_hidden: bool,
+}
+
+impl Default for StatusApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1".to_string(),
+            port: 8765,
+            allowed_origin: None,
+            include_hidden: false,
+        }
+    }
+}
+
+/// Files rendered from the shown session for stream overlays, e.g. OBS text and browser sources.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct NowPlayingConfig {
+    pub files: Vec<NowPlayingFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NowPlayingFile {
+    /// Values are HTML-escaped when this ends in `.html` or `.htm`
+    pub path: PathBuf,
+    pub template: PresenceTemplate,
+}
+
+/// Pauses the Discord presence without stopping the monitor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Incognito {
+    #[default]
+    Off,
+    On,
+    /// Until this unix time in seconds
+    Until {
+        until: u64,
+    },
+    /// Until the item playing when it was turned on stops or another one starts
+    UntilMediaEnds {
+        rating_key: Option<String>,
+        media_title: String,
+    },
+}
+
+impl Incognito {
+    pub fn until_media_ends(session: &Session) -> Self {
+        Incognito::UntilMediaEnds {
+            rating_key: session.rating_key.clone(),
+            media_title: session.media_title.clone(),
+        }
+    }
 }
 
 impl AppConfig {
     pub fn is_authenticated(&self) -> bool {
         self.auth_token.is_some() && self.username.is_some()
     }
+
+    /// Whose sessions are monitored and on which devices, `None` until signed in.
+    pub fn session_matcher(&self) -> Option<SessionMatcher> {
+        Some(SessionMatcher {
+            account_id: self.user_id,
+            username: self.username.clone()?,
+            additional: self.additional_users.clone(),
+            devices: self.devices.clone(),
+        })
+    }
+}
+
+/// Plex users tracked besides the signed-in account, such as a managed Plex Home profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AdditionalUsers {
+    /// plex.tv account IDs
+    pub ids: Vec<u64>,
+    /// User titles as servers report them, case-insensitive
+    pub titles: Vec<String>,
+}
+
+/// Players the presence is restricted to, matched by title, product, platform, device or
+/// machine identifier, case-insensitive. Off without values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct DeviceFilter {
+    pub mode: FilterMode,
+    pub values: Vec<String>,
+}
+
+impl DeviceFilter {
+    pub fn allows(&self, player: Option<&PlayerInfo>) -> bool {
+        if self.values.is_empty() {
+            return true;
+        }
+        let matched =
+            player.is_some_and(|player| self.values.iter().any(|value| player.matches(value)));
+        match self.mode {
+            FilterMode::Block => !matched,
+            FilterMode::Allow => matched,
+        }
+    }
+}
+
+/// Decides which sessions on a server are the user's and get monitored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionMatcher {
+    pub account_id: Option<u64>,
+    pub username: String,
+    pub additional: AdditionalUsers,
+    pub devices: DeviceFilter,
+}
+
+impl SessionMatcher {
+    /// Whether sessions of the user with `id` and `title` on `server` are tracked.
+    pub fn matches_user(&self, server: &PlexServer, id: Option<u64>, title: &str) -> bool {
+        if let Some(id) = id {
+            // Servers list their owner as user 1 rather than by plex.tv account ID
+            let is_account = Some(id) == self.account_id || (server.owned && id == 1);
+            if is_account || self.additional.ids.contains(&id) {
+                return true;
+            }
+        }
+        // The account's own title is only a fallback, users can rename themselves
+        let is_account = (self.account_id.is_none() || id.is_none()) && title == self.username;
+        is_account
+            || self
+                .additional
+                .titles
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(title))
+    }
+
+    pub fn matches_player(&self, player: Option<&PlayerInfo>) -> bool {
+        self.devices.allows(player)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlexAuth {
     pub auth_token: String,
     pub username: String,
+    /// plex.tv account ID
+    pub user_id: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +314,68 @@ pub struct PlexServer {
     pub address: String,
     pub port: u16,
     pub owned: bool,
+    /// Base URL of the chosen connection, `http://{address}:{port}` when unset
+    #[serde(default)]
+    pub uri: Option<String>,
+    #[serde(default)]
+    pub machine_identifier: Option<String>,
+    /// Every connection plex.tv advertises, probed to pick the one in use
+    #[serde(default)]
+    pub connections: Vec<ServerConnection>,
+}
+
+impl PlexServer {
+    /// Same address and port, regardless of the display name or ownership
+    pub fn is_same_endpoint(&self, other: &PlexServer) -> bool {
+        self.address == other.address && self.port == other.port
+    }
+
+    /// Same server, even if a different connection to it was picked
+    pub fn is_same_server(&self, other: &PlexServer) -> bool {
+        match (&self.machine_identifier, &other.machine_identifier) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.is_same_endpoint(other),
+        }
+    }
+
+    pub fn base_url(&self) -> String {
+        match &self.uri {
+            Some(uri) => uri.trim_end_matches('/').to_string(),
+            None => format!("http://{}:{}", self.address, self.port),
+        }
+    }
+
+    pub fn with_connection(&self, connection: &ServerConnection) -> PlexServer {
+        PlexServer {
+            address: connection.address.clone(),
+            port: connection.port,
+            uri: Some(connection.uri.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerConnection {
+    pub protocol: String,
+    pub address: String,
+    pub port: u16,
+    pub uri: String,
+    pub local: bool,
+    pub relay: bool,
+}
+
+impl ServerConnection {
+    /// Lower is preferred: local, then remote, then relay
+    pub fn preference(&self) -> u8 {
+        if self.relay {
+            2
+        } else if self.local {
+            0
+        } else {
+            1
+        }
+    }
 }
 
 impl std::fmt::Display for PlexServer {
@@ -84,6 +403,8 @@ pub enum MediaType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
+    pub session_key: Option<String>,
+    pub rating_key: Option<String>,
     pub media_title: String,
     pub media_index: Option<u32>,
     pub media_parent_title: Option<String>,
@@ -93,14 +414,392 @@ pub struct Session {
     pub media_type: MediaType,
     pub duration: u64,
     pub view_offset: u64,
+    /// Artwork path on the server, e.g. `/library/metadata/1/thumb/2`.
+    /// Only fetchable with the token, so it is never shown to Discord as is
     pub thumbnail: Option<String>,
+    pub year: Option<u32>,
+    pub library_section_id: Option<String>,
+    pub library_section_title: Option<String>,
+    /// External IDs such as `imdb://tt0111161`, `tmdb://278` or `tvdb://12345`
+    pub guids: Vec<String>,
+    pub genres: Vec<String>,
+    pub content_rating: Option<String>,
+    pub studio: Option<String>,
+    pub summary: Option<String>,
+    /// Release date as reported by Plex, e.g. `2019-05-19`
+    pub originally_available_at: Option<String>,
+    /// Machine identifier of the server playing it
+    #[serde(default)]
+    pub server_id: Option<String>,
+    /// The client playing it, as far as the server reported it
+    #[serde(default)]
+    pub player: Option<PlayerInfo>,
+}
+
+/// A Plex client, from the `Player` of a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PlayerInfo {
+    /// Name given to the device, e.g. `Living Room TV`
+    pub title: Option<String>,
+    /// App, e.g. `Plex for Android (TV)` or `Plexamp`
+    pub product: Option<String>,
+    pub platform: Option<String>,
+    pub device: Option<String>,
+    pub machine_identifier: Option<String>,
+}
+
+impl PlayerInfo {
+    /// Any of the fields equals `value`, case-insensitive.
+    pub fn matches(&self, value: &str) -> bool {
+        [
+            &self.title,
+            &self.product,
+            &self.platform,
+            &self.device,
+            &self.machine_identifier,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.eq_ignore_ascii_case(value))
+    }
+
+    /// Title, falling back to product and device, for display.
+    pub fn name(&self) -> Option<&str> {
+        self.title
+            .as_deref()
+            .or(self.product.as_deref())
+            .or(self.device.as_deref())
+    }
+}
+
+#[cfg(test)]
+impl Session {
+    /// A playing item with only the essentials set, for tests to adjust.
+    pub fn for_test(media_type: MediaType, title: &str) -> Self {
+        Session {
+            session_key: Some("1".to_string()),
+            rating_key: Some("100".to_string()),
+            media_title: title.to_string(),
+            media_index: None,
+            media_parent_title: None,
+            media_parent_index: None,
+            media_grandparent_title: None,
+            player_state: PlayerState::Playing,
+            media_type,
+            duration: 3_600_000,
+            view_offset: 0,
+            thumbnail: None,
+            year: None,
+            library_section_id: None,
+            library_section_title: None,
+            guids: Vec::new(),
+            genres: Vec::new(),
+            content_rating: None,
+            studio: None,
+            summary: None,
+            originally_available_at: None,
+            server_id: None,
+            player: None,
+        }
+    }
+}
+
+impl Session {
+    /// Same item in the same playback session, regardless of state or position
+    pub fn is_same_media(&self, other: &Session) -> bool {
+        self.session_key == other.session_key
+            && self.rating_key == other.rating_key
+            && self.media_title == other.media_title
+    }
+
+    /// ID part of the first Guid with this scheme, e.g. `tt0111161` for `imdb://tt0111161`.
+    pub fn guid(&self, scheme: &str) -> Option<&str> {
+        self.guids.iter().find_map(|guid| {
+            let (guid_scheme, id) = guid.split_once("://")?;
+            (guid_scheme == scheme && !id.is_empty()).then_some(id)
+        })
+    }
+}
+
+/// What changed in the monitored playback, published to every subscriber.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum MonitorEvent {
+    SessionStarted(Session),
+    Paused(Session),
+    Resumed(Session),
+    Seeked(Session),
+    /// The shown session switched to another item or another player
+    MediaChanged(Session),
+    Buffering(Session),
+    /// Playback stopped, carries the last known state of the session
+    Ended(Session),
+    DiscordDisconnected {
+        error: String,
+        session: Option<Session>,
+    },
+    ServerUnreachable {
+        server: String,
+        error: String,
+        /// Last session seen on that server, kept until it can be refreshed again
+        session: Option<Session>,
+    },
+    /// A timed incognito ran out, the presence is shown again
+    IncognitoEnded,
+}
+
+impl MonitorEvent {
+    pub fn filtered(self, presence_filter: Option<FilterAction>) -> FilteredEvent {
+        FilteredEvent {
+            event: self,
+            presence_filter,
+        }
+    }
+}
+
+/// A `MonitorEvent` with the privacy filter or incognito that kept its session off
+/// Discord when it happened. Published as is, consumers outside the app only get `shown`.
+#[derive(Debug, Clone)]
+pub struct FilteredEvent {
+    pub event: MonitorEvent,
+    pub presence_filter: Option<FilterAction>,
+}
+
+impl FilteredEvent {
+    /// The event as Discord showed it: playback of a hidden session is left out and
+    /// other events lose its session, generic sessions are replaced by their stand-in.
+    pub fn shown(&self) -> Option<MonitorEvent> {
+        let shown = |session: &Session| match self.presence_filter {
+            None => Some(session.clone()),
+            Some(FilterAction::Generic) => Some(generic_session(session)),
+            Some(FilterAction::Hide) => None,
+        };
+        let event = match &self.event {
+            MonitorEvent::SessionStarted(s) => MonitorEvent::SessionStarted(shown(s)?),
+            MonitorEvent::Paused(s) => MonitorEvent::Paused(shown(s)?),
+            MonitorEvent::Resumed(s) => MonitorEvent::Resumed(shown(s)?),
+            MonitorEvent::Seeked(s) => MonitorEvent::Seeked(shown(s)?),
+            MonitorEvent::MediaChanged(s) => MonitorEvent::MediaChanged(shown(s)?),
+            MonitorEvent::Buffering(s) => MonitorEvent::Buffering(shown(s)?),
+            MonitorEvent::Ended(s) => MonitorEvent::Ended(shown(s)?),
+            MonitorEvent::DiscordDisconnected { error, session } => {
+                MonitorEvent::DiscordDisconnected {
+                    error: error.clone(),
+                    session: session.as_ref().and_then(shown),
+                }
+            }
+            MonitorEvent::ServerUnreachable {
+                server,
+                error,
+                session,
+            } => MonitorEvent::ServerUnreachable {
+                server: server.clone(),
+                error: error.clone(),
+                session: session.as_ref().and_then(shown),
+            },
+            MonitorEvent::IncognitoEnded => MonitorEvent::IncognitoEnded,
+        };
+        Some(event)
+    }
+}
+
+impl std::fmt::Display for MonitorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MonitorEvent::SessionStarted(s) => write!(f, "Playing: {}", s.media_title),
+            MonitorEvent::Paused(s) => write!(f, "Paused: {}", s.media_title),
+            MonitorEvent::Resumed(s) => write!(f, "Resumed: {}", s.media_title),
+            MonitorEvent::Seeked(s) => write!(f, "Seeked: {}", s.media_title),
+            MonitorEvent::MediaChanged(s) => write!(f, "Now playing: {}", s.media_title),
+            MonitorEvent::Buffering(s) => write!(f, "Buffering: {}", s.media_title),
+            MonitorEvent::Ended(s) => write!(f, "Stopped: {}", s.media_title),
+            MonitorEvent::DiscordDisconnected { error, .. } => {
+                write!(f, "Discord disconnected: {}", error)
+            }
+            MonitorEvent::ServerUnreachable { server, error, .. } => {
+                write!(f, "{} unreachable: {}", server, error)
+            }
+            MonitorEvent::IncognitoEnded => write!(f, "Incognito ended"),
+        }
+    }
+}
+
+/// One completed viewing, recorded from monitor events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchHistoryEntry {
+    /// Unix time in seconds
+    pub started_at: u64,
+    /// Unix time in seconds
+    pub ended_at: u64,
+    /// Time spent playing, pauses and buffering excluded
+    pub watched_ms: u64,
+    /// Playback got past `FINISHED_THRESHOLD` of the duration
+    pub finished: bool,
+    pub session: Session,
+}
+
+impl WatchHistoryEntry {
+    /// Same share Plex uses to mark an item as watched.
+    pub const FINISHED_THRESHOLD: f64 = 0.9;
+}
+
+/// A `PlaySessionStateNotification` from the Plex WebSocket.
+/// Sent for every session on the server, not just the current user's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaySessionStateNotification {
+    pub session_key: String,
+    pub key: String,
+    pub rating_key: Option<String>,
+    pub client_identifier: Option<String>,
+    pub state: PlayerState,
+    pub view_offset: u64,
+}
+
+impl PlaySessionStateNotification {
+    /// All a notification tells about the player is its identifier.
+    pub fn player(&self) -> Option<PlayerInfo> {
+        self.client_identifier.as_ref().map(|id| PlayerInfo {
+            machine_identifier: Some(id.clone()),
+            ..PlayerInfo::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlexNotification {
+    PlaySessionState(PlaySessionStateNotification),
+    /// Any other `NotificationContainer` type (activity, timeline, status, ...)
+    Other(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ApplicationState {
+    /// `config.json` exists but couldn't be read, nothing is saved until resolved
+    ConfigError,
     Login,
     WaitingForAuth,
     ServerSelection,
     Verifying,
     Running,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(owned: bool) -> PlexServer {
+        PlexServer {
+            name: "Home".to_string(),
+            address: "192.168.1.10".to_string(),
+            port: 32400,
+            owned,
+            uri: None,
+            machine_identifier: None,
+            connections: Vec::new(),
+        }
+    }
+
+    fn matcher(account_id: Option<u64>) -> SessionMatcher {
+        SessionMatcher {
+            account_id,
+            username: "alice".to_string(),
+            additional: AdditionalUsers::default(),
+            devices: DeviceFilter::default(),
+        }
+    }
+
+    #[test]
+    fn matches_account_id() {
+        let matcher = matcher(Some(12345678));
+
+        assert!(matcher.matches_user(&server(false), Some(12345678), "alice"));
+        // Renamed accounts still match by ID
+        assert!(matcher.matches_user(&server(false), Some(12345678), "alice_new"));
+        assert!(!matcher.matches_user(&server(false), Some(87654321), "bob"));
+    }
+
+    #[test]
+    fn owner_is_user_one_only_on_owned_servers() {
+        let matcher = matcher(Some(12345678));
+
+        assert!(matcher.matches_user(&server(true), Some(1), "alice"));
+        assert!(!matcher.matches_user(&server(false), Some(1), "alice"));
+    }
+
+    #[test]
+    fn title_is_only_a_fallback_without_ids() {
+        // Another user who took the old username isn't mistaken for the account
+        assert!(!matcher(Some(12345678)).matches_user(&server(true), Some(38219044), "alice"));
+        // Sessions without a user ID
+        assert!(matcher(Some(12345678)).matches_user(&server(true), None, "alice"));
+        // Configs from before the account ID was stored
+        assert!(matcher(None).matches_user(&server(true), Some(38219044), "alice"));
+        // The account's own title is compared exactly
+        assert!(!matcher(None).matches_user(&server(true), None, "Alice"));
+    }
+
+    #[test]
+    fn matches_additional_users() {
+        let mut matcher = matcher(Some(12345678));
+        matcher.additional = AdditionalUsers {
+            ids: vec![38219044],
+            titles: vec!["Kids".to_string()],
+        };
+
+        assert!(matcher.matches_user(&server(true), Some(38219044), "bob"));
+        assert!(matcher.matches_user(&server(true), Some(55555555), "kids"));
+        assert!(matcher.matches_user(&server(true), None, "KIDS"));
+        assert!(!matcher.matches_user(&server(true), Some(55555555), "guest"));
+    }
+
+    fn player(title: &str, product: &str, id: &str) -> PlayerInfo {
+        PlayerInfo {
+            title: Some(title.to_string()),
+            product: Some(product.to_string()),
+            platform: Some("iOS".to_string()),
+            device: Some("iPhone".to_string()),
+            machine_identifier: Some(id.to_string()),
+        }
+    }
+
+    fn filter(mode: FilterMode, values: &[&str]) -> DeviceFilter {
+        DeviceFilter {
+            mode,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn device_filter_without_values_allows_everything() {
+        for mode in [FilterMode::Block, FilterMode::Allow] {
+            assert!(filter(mode, &[]).allows(Some(&player("Phone", "Plexamp", "a1"))));
+            assert!(filter(mode, &[]).allows(None));
+        }
+    }
+
+    #[test]
+    fn device_filter_blocks_matching_players() {
+        let filter = filter(FilterMode::Block, &["plexamp", "Living Room TV"]);
+
+        assert!(!filter.allows(Some(&player("Phone", "Plexamp", "a1"))));
+        assert!(!filter.allows(Some(&player("living room tv", "Plex for LG", "b2"))));
+        assert!(filter.allows(Some(&player("Laptop", "Plex Web", "c3"))));
+        // Nothing to match against, so nothing is blocked
+        assert!(filter.allows(None));
+    }
+
+    #[test]
+    fn device_filter_allows_only_matching_players() {
+        let filter = filter(FilterMode::Allow, &["Living Room TV", "B2"]);
+
+        assert!(filter.allows(Some(&player("Living Room TV", "Plex for LG", "x"))));
+        // Any field counts, including the machine identifier
+        assert!(filter.allows(Some(&PlayerInfo {
+            machine_identifier: Some("b2".to_string()),
+            ..PlayerInfo::default()
+        })));
+        assert!(!filter.allows(Some(&player("Phone", "Plexamp", "a1"))));
+        assert!(!filter.allows(None));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::domain::models::{MediaType, Session};
+use serde::{Deserialize, Serialize};
+
+/// What is shown on Discord instead of a filtered session.
+/// Ordered by strength, the strongest action of all matching rules applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// A generic "Watching something" activity without any details, "Listening to something" for music
+    Generic,
+    /// No presence at all, as if nothing was playing
+    #[default]
+    Hide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterMode {
+    /// Sessions matching one of the values are filtered
+    #[default]
+    Block,
+    /// Only sessions matching one of the values are shown
+    Allow,
+}
+
+/// A block- or allowlist. Rules without values are off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterRule<T> {
+    pub mode: FilterMode,
+    pub values: Vec<T>,
+    pub action: FilterAction,
+}
+
+impl<T> Default for FilterRule<T> {
+    fn default() -> Self {
+        Self {
+            mode: FilterMode::default(),
+            values: Vec::new(),
+            action: FilterAction::default(),
+        }
+    }
+}
+
+impl<T> FilterRule<T> {
+    fn filters(&self, matches: impl FnMut(&T) -> bool) -> Option<FilterAction> {
+        if self.values.is_empty() {
+            return None;
+        }
+        let matched = self.values.iter().any(matches);
+        let filtered = match self.mode {
+            FilterMode::Block => matched,
+            FilterMode::Allow => !matched,
+        };
+        filtered.then_some(self.action)
+    }
+}
+
+/// A content rating such as `PG-13`, `TV-MA` or `de/16`, compared by minimum age.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ContentRating {
+    source: String,
+    age: u8,
+}
+
+impl ContentRating {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        match rating_age(source) {
+            Some(age) => Ok(Self {
+                source: source.to_string(),
+                age,
+            }),
+            None => Err(format!("Unknown content rating '{}'", source)),
+        }
+    }
+}
+
+impl TryFrom<String> for ContentRating {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<ContentRating> for String {
+    fn from(rating: ContentRating) -> Self {
+        rating.source
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ContentRatingRule {
+    /// Highest rating shown as is, the rule is off when unset
+    pub max: Option<ContentRating>,
+    /// Also filter sessions without a rating this rule understands
+    pub filter_unrated: bool,
+    pub action: FilterAction,
+}
+
+/// Rules deciding which sessions are kept off Discord.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PrivacyFilters {
+    /// Library section titles or IDs
+    pub libraries: FilterRule<String>,
+    /// Titles of the item, its show, season, album or artist, case-insensitive
+    pub titles: FilterRule<String>,
+    pub media_types: FilterRule<MediaType>,
+    pub content_rating: ContentRatingRule,
+}
+
+impl PrivacyFilters {
+    /// Strongest action of the rules that filter `session`, `None` if it can be shown.
+    pub fn evaluate(&self, session: &Session) -> Option<FilterAction> {
+        let library = self.libraries.filters(|library| {
+            session
+                .library_section_title
+                .as_ref()
+                .is_some_and(|title| title.eq_ignore_ascii_case(library))
+                || session.library_section_id.as_deref() == Some(library.as_str())
+        });
+        let title = self.titles.filters(|title| {
+            [
+                Some(&session.media_title),
+                session.media_parent_title.as_ref(),
+                session.media_grandparent_title.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|t| t.to_lowercase() == title.to_lowercase())
+        });
+        let media_type = self
+            .media_types
+            .filters(|media_type| *media_type == session.media_type);
+        let content_rating = self.content_rating.max.as_ref().and_then(|max| {
+            let filtered = match session.content_rating.as_deref().and_then(rating_age) {
+                Some(age) => age > max.age,
+                None => self.content_rating.filter_unrated,
+            };
+            filtered.then_some(self.content_rating.action)
+        });
+
+        [library, title, media_type, content_rating]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+}
+
+/// Minimum viewer age for the US film and TV ratings Plex uses, and for numeric
+/// ratings with an optional country prefix such as `de/16` or `gb/12A`.
+fn rating_age(rating: &str) -> Option<u8> {
+    let rating = rating.trim().to_ascii_uppercase();
+    let rating = rating.split_once('/').map_or(rating.as_str(), |(_, r)| r);
+    let age = match rating {
+        "G" | "U" | "TV-Y" | "TV-G" | "APPROVED" => 0,
+        "TV-Y7" | "TV-Y7-FV" => 7,
+        "PG" | "TV-PG" => 10,
+        "PG-13" => 13,
+        "TV-14" => 14,
+        "R" | "TV-MA" => 17,
+        "NC-17" | "X" => 18,
+        _ => {
+            // `16`, `FSK 16`, `12A`, `R18`
+            return rating
+                .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                .trim_start_matches(|c: char| !c.is_ascii_digit())
+                .parse()
+                .ok();
+        }
+    };
+    Some(age)
+}
+
+/// Stand-in for a session shown with `FilterAction::Generic`. Only the player state is kept.
+pub fn generic_session(session: &Session) -> Session {
+    Session {
+        session_key: None,
+        rating_key: None,
+        media_title: match session.media_type {
+            MediaType::Track => "Listening to something",
+            _ => "Watching something",
+        }
+        .to_string(),
+        media_index: None,
+        media_parent_title: None,
+        media_parent_index: None,
+        media_grandparent_title: None,
+        player_state: session.player_state.clone(),
+        media_type: MediaType::Unknown,
+        // No progress bar, the length could give the item away
+        duration: 0,
+        view_offset: 0,
+        thumbnail: None,
+        year: None,
+        library_section_id: None,
+        library_section_title: None,
+        guids: Vec::new(),
+        genres: Vec::new(),
+        content_rating: None,
+        studio: None,
+        summary: None,
+        originally_available_at: None,
+        server_id: None,
+        player: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::PlayerState;
+
+    fn rule<T>(mode: FilterMode, values: Vec<T>, action: FilterAction) -> FilterRule<T> {
+        FilterRule {
+            mode,
+            values,
+            action,
+        }
+    }
+
+    fn max_rating(max: &str, filter_unrated: bool) -> ContentRatingRule {
+        ContentRatingRule {
+            max: Some(ContentRating::parse(max).unwrap()),
+            filter_unrated,
+            action: FilterAction::Hide,
+        }
+    }
+
+    fn movie(rating: Option<&str>) -> Session {
+        let mut session = Session::for_test(MediaType::Movie, "Heat");
+        session.library_section_id = Some("1".to_string());
+        session.library_section_title = Some("Movies".to_string());
+        session.content_rating = rating.map(str::to_string);
+        session
+    }
+
+    #[test]
+    fn no_rules_show_everything() {
+        assert_eq!(PrivacyFilters::default().evaluate(&movie(None)), None);
+    }
+
+    #[test]
+    fn blocklists_filter_matching_sessions() {
+        let filters = PrivacyFilters {
+            libraries: rule(
+                FilterMode::Block,
+                vec!["movies".to_string()],
+                FilterAction::Hide,
+            ),
+            ..Default::default()
+        };
+        assert_eq!(filters.evaluate(&movie(None)), Some(FilterAction::Hide));
+
+        let mut other = movie(None);
+        other.library_section_title = Some("Home Videos".to_string());
+        other.library_section_id = Some("2".to_string());
+        assert_eq!(filters.evaluate(&other), None);
+    }
+
+    #[test]
+    fn allowlists_filter_everything_else() {
+        let filters = PrivacyFilters {
+            media_types: rule(
+                FilterMode::Allow,
+                vec![MediaType::Track],
+                FilterAction::Generic,
+            ),
+            ..Default::default()
+        };
+        assert_eq!(filters.evaluate(&movie(None)), Some(FilterAction::Generic));
+        assert_eq!(
+            filters.evaluate(&Session::for_test(MediaType::Track, "Bohemian Rhapsody")),
+            None
+        );
+    }
+
+    #[test]
+    fn titles_match_the_show_case_insensitively() {
+        let filters = PrivacyFilters {
+            titles: rule(
+                FilterMode::Block,
+                vec!["the office".to_string()],
+                FilterAction::Hide,
+            ),
+            ..Default::default()
+        };
+        let mut episode = Session::for_test(MediaType::Episode, "Pilot");
+        episode.media_grandparent_title = Some("The Office".to_string());
+        assert_eq!(filters.evaluate(&episode), Some(FilterAction::Hide));
+    }
+
+    #[test]
+    fn hide_wins_over_generic() {
+        let filters = PrivacyFilters {
+            libraries: rule(
+                FilterMode::Block,
+                vec!["1".to_string()],
+                FilterAction::Generic,
+            ),
+            media_types: rule(
+                FilterMode::Block,
+                vec![MediaType::Movie],
+                FilterAction::Hide,
+            ),
+            ..Default::default()
+        };
+        assert_eq!(filters.evaluate(&movie(None)), Some(FilterAction::Hide));
+
+        let filters = PrivacyFilters {
+            media_types: rule(
+                FilterMode::Block,
+                vec![MediaType::Movie],
+                FilterAction::Generic,
+            ),
+            ..filters
+        };
+        assert_eq!(filters.evaluate(&movie(None)), Some(FilterAction::Generic));
+    }
+
+    #[test]
+    fn content_ratings_above_the_maximum_are_filtered() {
+        let filters = PrivacyFilters {
+            content_rating: max_rating("PG-13", false),
+            ..Default::default()
+        };
+        assert_eq!(filters.evaluate(&movie(Some("PG-13"))), None);
+        assert_eq!(filters.evaluate(&movie(Some("PG"))), None);
+        assert_eq!(
+            filters.evaluate(&movie(Some("R"))),
+            Some(FilterAction::Hide)
+        );
+        assert_eq!(
+            filters.evaluate(&movie(Some("de/16"))),
+            Some(FilterAction::Hide)
+        );
+        assert_eq!(filters.evaluate(&movie(Some("NR"))), None);
+        assert_eq!(filters.evaluate(&movie(None)), None);
+    }
+
+    #[test]
+    fn unrated_sessions_are_filtered_on_request() {
+        let filters = PrivacyFilters {
+            content_rating: max_rating("TV-14", true),
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.evaluate(&movie(Some("NR"))),
+            Some(FilterAction::Hide)
+        );
+        assert_eq!(filters.evaluate(&movie(None)), Some(FilterAction::Hide));
+        assert_eq!(filters.evaluate(&movie(Some("TV-PG"))), None);
+    }
+
+    #[test]
+    fn rating_ages() {
+        assert_eq!(rating_age("G"), Some(0));
+        assert_eq!(rating_age("tv-y7"), Some(7));
+        assert_eq!(rating_age("PG-13"), Some(13));
+        assert_eq!(rating_age("TV-MA"), Some(17));
+        assert_eq!(rating_age("NC-17"), Some(18));
+        assert_eq!(rating_age("de/16"), Some(16));
+        assert_eq!(rating_age("gb/12A"), Some(12));
+        assert_eq!(rating_age("FSK 16"), Some(16));
+        assert_eq!(rating_age("R18"), Some(18));
+        assert_eq!(rating_age("NR"), None);
+        assert_eq!(rating_age(""), None);
+        assert!(ContentRating::parse("Not Rated").is_err());
+    }
+
+    #[test]
+    fn generic_sessions_keep_only_the_player_state() {
+        let mut session = movie(Some("R"));
+        session.player_state = PlayerState::Paused;
+        let generic = generic_session(&session);
+        assert_eq!(generic.media_title, "Watching something");
+        assert_eq!(generic.player_state, PlayerState::Paused);
+        assert_eq!(generic.media_type, MediaType::Unknown);
+        assert_eq!(generic.duration, 0);
+        assert_eq!(generic.library_section_title, None);
+
+        let track = Session::for_test(MediaType::Track, "Bohemian Rhapsody");
+        assert_eq!(
+            generic_session(&track).media_title,
+            "Listening to something"
+        );
+    }
+}
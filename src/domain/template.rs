@@ -0,0 +1,467 @@
+use crate::domain::models::{MediaType, PlayerState, Session};
+use serde::{Deserialize, Serialize};
+
+/// Names accepted inside `{...}`, in the order they are listed in error messages.
+const PLACEHOLDERS: &[&str] = &[
+    "title",
+    "show",
+    "season",
+    "episode",
+    "season_title",
+    "artist",
+    "album",
+    "parent_title",
+    "grandparent_title",
+    "year",
+    "library",
+    "studio",
+    "genre",
+    "genres",
+    "content_rating",
+    "release_date",
+    "summary",
+    "duration",
+    "position",
+    "progress",
+    "percent",
+    "state",
+    "type",
+    "artwork",
+];
+
+/// A presence text template such as `{show} ({year})`.
+///
+/// `{name}` is replaced by a `Session` field, `[...]` is only rendered when every
+/// placeholder inside it has a value, and `{{`, `}}`, `[[`, `]]` are literal brackets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PresenceTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+    Optional(Vec<Segment>),
+}
+
+impl PresenceTemplate {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut root: Vec<Segment> = Vec::new();
+        let mut optional: Option<Vec<Segment>> = None;
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' | '[' | ']' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    literal.push(c);
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err(format!("unclosed '{{' in template '{}'", source))
+                            }
+                            Some(ch) => name.push(ch),
+                        }
+                    }
+                    let name = name.trim().to_string();
+                    if !PLACEHOLDERS.contains(&name.as_str()) {
+                        return Err(format!(
+                            "unknown placeholder '{{{}}}' in template '{}' (available: {})",
+                            name,
+                            source,
+                            PLACEHOLDERS.join(", ")
+                        ));
+                    }
+                    let target = optional.as_mut().unwrap_or(&mut root);
+                    flush_literal(&mut literal, target);
+                    target.push(Segment::Placeholder(name));
+                }
+                '}' => {
+                    return Err(format!(
+                        "unmatched '}}' in template '{}' (use '}}}}' for a literal brace)",
+                        source
+                    ))
+                }
+                '[' => {
+                    if optional.is_some() {
+                        return Err(format!(
+                            "optional sections can't be nested in template '{}'",
+                            source
+                        ));
+                    }
+                    flush_literal(&mut literal, &mut root);
+                    optional = Some(Vec::new());
+                }
+                ']' => match optional.take() {
+                    Some(mut segments) => {
+                        flush_literal(&mut literal, &mut segments);
+                        root.push(Segment::Optional(segments));
+                    }
+                    None => {
+                        return Err(format!(
+                            "unmatched ']' in template '{}' (use ']]' for a literal bracket)",
+                            source
+                        ))
+                    }
+                },
+                _ => literal.push(c),
+            }
+        }
+
+        if optional.is_some() {
+            return Err(format!("unclosed '[' in template '{}'", source));
+        }
+        flush_literal(&mut literal, &mut root);
+
+        Ok(Self {
+            source: source.to_string(),
+            segments: root,
+        })
+    }
+
+    pub fn render(&self, session: &Session) -> String {
+        self.render_with(session, None, str::to_string)
+    }
+
+    /// Renders with the resolved `artwork` URL, passing every inserted value through
+    /// `escape` so it can't break out of markup around it.
+    pub fn render_with(
+        &self,
+        session: &Session,
+        artwork: Option<&str>,
+        escape: impl Fn(&str) -> String,
+    ) -> String {
+        let value = |name: &str| placeholder_value(name, session, artwork).map(|v| escape(&v));
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Placeholder(name) => out.push_str(&value(name).unwrap_or_default()),
+                Segment::Optional(segments) => {
+                    let mut section = String::new();
+                    let complete = segments.iter().all(|s| match s {
+                        Segment::Literal(text) => {
+                            section.push_str(text);
+                            true
+                        }
+                        Segment::Placeholder(name) => match value(name) {
+                            Some(value) => {
+                                section.push_str(&value);
+                                true
+                            }
+                            None => false,
+                        },
+                        Segment::Optional(_) => unreachable!("nested sections are rejected"),
+                    });
+                    if complete {
+                        out.push_str(&section);
+                    }
+                }
+            }
+        }
+        out.trim().to_string()
+    }
+}
+
+fn flush_literal(literal: &mut String, target: &mut Vec<Segment>) {
+    if !literal.is_empty() {
+        target.push(Segment::Literal(std::mem::take(literal)));
+    }
+}
+
+/// Empty strings count as missing so optional sections collapse.
+fn placeholder_value(name: &str, session: &Session, artwork: Option<&str>) -> Option<String> {
+    let value = match name {
+        "title" => Some(session.media_title.clone()),
+        "show" | "artist" | "grandparent_title" => session.media_grandparent_title.clone(),
+        "season_title" | "album" | "parent_title" => session.media_parent_title.clone(),
+        "season" => session.media_parent_index.map(|i| i.to_string()),
+        "episode" => session.media_index.map(|i| i.to_string()),
+        "year" => session.year.map(|y| y.to_string()),
+        "library" => session.library_section_title.clone(),
+        "studio" => session.studio.clone(),
+        "genre" => session.genres.first().cloned(),
+        "genres" => Some(session.genres.join(", ")),
+        "content_rating" => session.content_rating.clone(),
+        "release_date" => session.originally_available_at.clone(),
+        "summary" => session.summary.clone(),
+        "duration" => (session.duration > 0).then(|| format_duration(session.duration)),
+        "position" => (session.duration > 0).then(|| format_duration(session.view_offset)),
+        "progress" => (session.duration > 0).then(|| {
+            format!(
+                "{} / {}",
+                format_duration(session.view_offset),
+                format_duration(session.duration)
+            )
+        }),
+        "percent" => (session.duration > 0).then(|| {
+            (session.view_offset.min(session.duration) * 100 / session.duration).to_string()
+        }),
+        "state" => Some(
+            match session.player_state {
+                PlayerState::Playing => "Playing",
+                PlayerState::Paused => "Paused",
+                PlayerState::Buffering => "Buffering",
+                PlayerState::Idle => "Idle",
+            }
+            .to_string(),
+        ),
+        "type" => Some(
+            match session.media_type {
+                MediaType::Episode => "Episode",
+                MediaType::Movie => "Movie",
+                MediaType::Track => "Track",
+                MediaType::Unknown | MediaType::Idle => "Media",
+            }
+            .to_string(),
+        ),
+        "artwork" => artwork.map(str::to_string),
+        _ => None,
+    };
+    value.filter(|v| !v.is_empty())
+}
+
+/// `1:02:03` for an hour or more, otherwise `2:03`.
+fn format_duration(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+impl TryFrom<String> for PresenceTemplate {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<PresenceTemplate> for String {
+    fn from(template: PresenceTemplate) -> Self {
+        template.source
+    }
+}
+
+/// The three presence text fields for one media type.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresenceFormat {
+    pub details: PresenceTemplate,
+    pub state: PresenceTemplate,
+    pub large_text: PresenceTemplate,
+}
+
+impl PresenceFormat {
+    fn new(details: &str, state: &str, large_text: &str) -> Self {
+        // Only called with the built-in defaults below
+        Self {
+            details: PresenceTemplate::parse(details).expect("valid default template"),
+            state: PresenceTemplate::parse(state).expect("valid default template"),
+            large_text: PresenceTemplate::parse(large_text).expect("valid default template"),
+        }
+    }
+}
+
+/// Fields left out in `config.json` keep the default of their media type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "PartialTemplates")]
+pub struct PresenceTemplates {
+    pub episode: PresenceFormat,
+    pub movie: PresenceFormat,
+    pub track: PresenceFormat,
+    pub unknown: PresenceFormat,
+}
+
+impl PresenceTemplates {
+    pub fn for_media_type(&self, media_type: &MediaType) -> &PresenceFormat {
+        match media_type {
+            MediaType::Episode => &self.episode,
+            MediaType::Movie => &self.movie,
+            MediaType::Track => &self.track,
+            MediaType::Unknown | MediaType::Idle => &self.unknown,
+        }
+    }
+}
+
+impl Default for PresenceTemplates {
+    fn default() -> Self {
+        Self {
+            episode: PresenceFormat::new("[S{season} · E{episode} — ]{title}", "{show}", "{title}"),
+            movie: PresenceFormat::new("{title}", "", "{title}"),
+            track: PresenceFormat::new("{title}", "{artist}", "{title}"),
+            unknown: PresenceFormat::new(
+                "{grandparent_title}[ - {parent_title}]",
+                "{title}",
+                "{title}",
+            ),
+        }
+    }
+}
+
+/// `PresenceFormat` as written in `config.json`, where any field can be left out.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PartialFormat {
+    details: Option<PresenceTemplate>,
+    state: Option<PresenceTemplate>,
+    large_text: Option<PresenceTemplate>,
+}
+
+impl PartialFormat {
+    fn or(self, default: PresenceFormat) -> PresenceFormat {
+        PresenceFormat {
+            details: self.details.unwrap_or(default.details),
+            state: self.state.unwrap_or(default.state),
+            large_text: self.large_text.unwrap_or(default.large_text),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PartialTemplates {
+    episode: PartialFormat,
+    movie: PartialFormat,
+    track: PartialFormat,
+    unknown: PartialFormat,
+}
+
+impl From<PartialTemplates> for PresenceTemplates {
+    fn from(partial: PartialTemplates) -> Self {
+        let defaults = PresenceTemplates::default();
+        Self {
+            episode: partial.episode.or(defaults.episode),
+            movie: partial.movie.or(defaults.movie),
+            track: partial.track.or(defaults.track),
+            unknown: partial.unknown.or(defaults.unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode() -> Session {
+        let mut session = Session::for_test(MediaType::Episode, "Negro y Azul");
+        session.media_grandparent_title = Some("Breaking Bad".to_string());
+        session.media_parent_title = Some("Season 2".to_string());
+        session.media_parent_index = Some(2);
+        session.media_index = Some(7);
+        session.year = Some(2009);
+        session.duration = 2_863_000;
+        session.view_offset = 912_000;
+        session
+    }
+
+    fn render(template: &str, session: &Session) -> String {
+        PresenceTemplate::parse(template).unwrap().render(session)
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        assert_eq!(
+            render("{show} S{season}E{episode}: {title} ({year})", &episode()),
+            "Breaking Bad S2E7: Negro y Azul (2009)"
+        );
+        assert_eq!(
+            render("{progress} · {percent}% · { state }", &episode()),
+            "15:12 / 47:43 · 31% · Playing"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        let error = PresenceTemplate::parse("{title} ({released})").unwrap_err();
+
+        assert!(error.contains("unknown placeholder '{released}'"));
+        assert!(error.contains("available: title, show"));
+    }
+
+    #[test]
+    fn optional_sections_collapse_without_a_value() {
+        let template = "{title}[ ({year})][ · {studio}]";
+
+        assert_eq!(render(template, &episode()), "Negro y Azul (2009)");
+
+        let mut session = episode();
+        session.year = None;
+        assert_eq!(render(template, &session), "Negro y Azul");
+
+        // Empty values count as missing
+        session.media_grandparent_title = Some(String::new());
+        assert_eq!(render("[{show} - ]{title}", &session), "Negro y Azul");
+        // Without a duration there's no progress to show
+        session.duration = 0;
+        assert_eq!(render("{title}[ {progress}]", &session), "Negro y Azul");
+    }
+
+    #[test]
+    fn doubled_brackets_are_literal() {
+        assert_eq!(
+            render("{{{title}}} [[{year}]]", &episode()),
+            "{Negro y Azul} [2009]"
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_brackets() {
+        for source in [
+            "{title",
+            "title}",
+            "[{title}",
+            "{title}]",
+            "[[{year}]",
+            "[a [b] c]",
+            "{ti{tle}",
+        ] {
+            assert!(
+                PresenceTemplate::parse(source).is_err(),
+                "'{}' should not parse",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn escapes_inserted_values_only() {
+        let mut session = episode();
+        session.media_title = "<b>Tom & Jerry</b>".to_string();
+        let template = PresenceTemplate::parse("<i>{title}</i>").unwrap();
+
+        let escaped = template.render_with(&session, None, |v| v.replace('<', "&lt;"));
+
+        assert_eq!(escaped, "<i>&lt;b>Tom & Jerry&lt;/b></i>");
+    }
+
+    #[test]
+    fn missing_format_fields_keep_their_defaults() {
+        let templates: PresenceTemplates =
+            serde_json::from_str(r#"{"movie": {"details": "{title} ({year})"}}"#).unwrap();
+        let defaults = PresenceTemplates::default();
+
+        assert_eq!(templates.movie.details.source, "{title} ({year})");
+        assert_eq!(templates.movie.state, defaults.movie.state);
+        assert_eq!(templates.movie.large_text, defaults.movie.large_text);
+        assert_eq!(templates.episode, defaults.episode);
+    }
+
+    #[test]
+    fn invalid_templates_fail_to_load() {
+        let result =
+            serde_json::from_str::<PresenceTemplates>(r#"{"movie": {"details": "{titel}"}}"#);
+
+        assert!(result.is_err());
+    }
+}
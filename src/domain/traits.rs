@@ -8,36 +8,86 @@ use mockall::automock;
 pub trait PlexClient: Send + Sync {
     async fn start_oauth_flow(&self) -> AppResult<OAuthPinInfo>;
     async fn check_oauth_status(&self, pin_id: u64) -> AppResult<Option<PlexAuth>>;
+    // Direct credentials login, for machines without a browser
+    async fn sign_in_with_credentials(
+        &self,
+        login: &str,
+        password: &str,
+        verification_code: Option<String>,
+    ) -> AppResult<PlexAuth>;
+    // Account the token belongs to, from /api/v2/user
+    async fn get_account(&self, auth_token: &str) -> AppResult<PlexAuth>;
     async fn get_servers(&self, auth_token: &str) -> AppResult<Vec<PlexServer>>;
+    // Players registered with the account on plex.tv, from /api/v2/resources
+    async fn get_players(&self, auth_token: &str) -> AppResult<Vec<PlayerInfo>>;
+    // Quick reachability check of a connection base URL against /identity. A rejected
+    // token only counts as unauthorized on `owned` servers, like everywhere else.
+    async fn check_connection(&self, uri: &str, auth_token: &str, owned: bool) -> AppResult<()>;
+    // Every session of the tracked users and players, in response order
     async fn get_sessions(
         &self,
         server: &PlexServer,
         auth_token: &str,
-        username: &str,
+        matcher: &SessionMatcher,
+    ) -> AppResult<Vec<Session>>;
+
+    // Resolves a notification via /library/metadata/{key}, for servers we don't own
+    async fn get_session_from_notification(
+        &self,
+        server: &PlexServer,
+        auth_token: &str,
+        notification: &PlaySessionStateNotification,
     ) -> AppResult<Option<Session>>;
 
-    // Returns a stream or receiver. For simplicity effectively, just a receiver via a callback or channel?
-    // Let's return a tokio::sync::mpsc::Receiver<()>. Signaling "something changed".
-    // Or just a loop helper?
-    // "Listen" implies streams.
+    // JPEG of an artwork path such as `Session::thumbnail`, scaled down by the server
+    async fn get_artwork(
+        &self,
+        server: &PlexServer,
+        auth_token: &str,
+        path: &str,
+    ) -> AppResult<Vec<u8>>;
+
+    // Receiver of parsed WebSocket notifications, closed when the socket drops.
+    // They cover every user of the server, not just the signed-in account.
     async fn listen_for_notifications(
         &self,
         server: &PlexServer,
         auth_token: &str,
-        username: &str,
-    ) -> AppResult<tokio::sync::mpsc::Receiver<()>>;
+    ) -> AppResult<tokio::sync::mpsc::Receiver<PlexNotification>>;
 }
 
 #[cfg_attr(test, automock)]
 pub trait DiscordClient: Send + Sync {
     fn connect(&mut self) -> AppResult<()>;
-    fn update_presence(&mut self, session: &Session) -> AppResult<()>;
+    // `artwork` is a Discord asset key or a public image URL
+    fn update_presence(&mut self, session: &Session, artwork: Option<String>) -> AppResult<()>;
     fn clear_presence(&mut self) -> AppResult<()>;
     fn is_connected(&self) -> bool;
 }
 
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait ArtworkResolver: Send + Sync {
+    // Discord asset key or public image URL for the session, never a URL to the Plex server
+    async fn resolve(
+        &self,
+        session: &Session,
+        server: &PlexServer,
+        auth_token: &str,
+    ) -> AppResult<Option<String>>;
+}
+
 #[cfg_attr(test, automock)]
 pub trait ConfigStore: Send + Sync {
     fn load(&self) -> AppResult<AppConfig>;
     fn save(&self, config: &AppConfig) -> AppResult<()>;
+    // Moves an unreadable config aside so the next load starts fresh
+    fn reset(&self) -> AppResult<()>;
+}
+
+#[cfg_attr(test, automock)]
+pub trait HistoryStore: Send + Sync {
+    fn append(&self, entry: &WatchHistoryEntry) -> AppResult<()>;
+    // Oldest first
+    fn load(&self) -> AppResult<Vec<WatchHistoryEntry>>;
 }
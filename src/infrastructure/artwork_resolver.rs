@@ -0,0 +1,540 @@
+use crate::domain::models::*;
+use crate::domain::traits::{ArtworkResolver, PlexClient};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TMDB_API: &str = "https://api.themoviedb.org/3";
+const TMDB_IMAGES: &str = "https://image.tmdb.org/t/p/w500";
+const MUSICBRAINZ_API: &str = "https://musicbrainz.org/ws/2";
+const COVER_ART_ARCHIVE: &str = "https://coverartarchive.org";
+/// Resolved items remembered before the cache starts over.
+const CACHE_CAPACITY: usize = 256;
+/// Longest a lookup may hold up a presence update, lookups make several requests.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long an item whose lookup failed is shown without artwork before retrying.
+const FAILURE_TTL: Duration = Duration::from_secs(300);
+
+/// Builds the resolver for `config`, cached unless it's a fixed asset.
+pub fn from_config(
+    config: &ArtworkConfig,
+    plex_client: Arc<dyn PlexClient>,
+) -> AppResult<Arc<dyn ArtworkResolver>> {
+    let resolver: Arc<dyn ArtworkResolver> = match config {
+        ArtworkConfig::None => Arc::new(StaticArtwork { asset_key: None }),
+        ArtworkConfig::Static { asset_key } => Arc::new(StaticArtwork {
+            asset_key: Some(asset_key.clone()),
+        }),
+        ArtworkConfig::External { tmdb_api_key } => {
+            Arc::new(CachedArtwork::new(Box::new(ExternalArtwork {
+                http_client: http_client()?,
+                tmdb_api_key: tmdb_api_key.clone(),
+            })))
+        }
+        ArtworkConfig::Upload {
+            endpoint,
+            authorization,
+        } => Arc::new(CachedArtwork::new(Box::new(UploadArtwork {
+            http_client: http_client()?,
+            plex_client,
+            endpoint: endpoint.clone(),
+            authorization: authorization.clone(),
+        }))),
+    };
+    Ok(resolver)
+}
+
+fn http_client() -> AppResult<Client> {
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        // MusicBrainz asks every client to identify itself
+        .user_agent(concat!("plex-discord-rpc/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| AppError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// The same asset for everything, or no image at all.
+pub struct StaticArtwork {
+    asset_key: Option<String>,
+}
+
+#[async_trait]
+impl ArtworkResolver for StaticArtwork {
+    async fn resolve(
+        &self,
+        _session: &Session,
+        _server: &PlexServer,
+        _auth_token: &str,
+    ) -> AppResult<Option<String>> {
+        Ok(self.asset_key.clone())
+    }
+}
+
+/// Remembers what `inner` resolved per item, including items without artwork.
+/// Lookups are cut off after a timeout; failed ones are shown without artwork
+/// and only retried once `failure_ttl` has passed.
+pub struct CachedArtwork {
+    inner: Box<dyn ArtworkResolver>,
+    timeout: Duration,
+    failure_ttl: Duration,
+    entries: Mutex<HashMap<String, Option<String>>>,
+    failures: Mutex<HashMap<String, Instant>>,
+}
+
+impl CachedArtwork {
+    pub fn new(inner: Box<dyn ArtworkResolver>) -> Self {
+        Self::with_limits(inner, RESOLVE_TIMEOUT, FAILURE_TTL)
+    }
+
+    fn with_limits(
+        inner: Box<dyn ArtworkResolver>,
+        timeout: Duration,
+        failure_ttl: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            timeout,
+            failure_ttl,
+            entries: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl ArtworkResolver for CachedArtwork {
+    async fn resolve(
+        &self,
+        session: &Session,
+        server: &PlexServer,
+        auth_token: &str,
+    ) -> AppResult<Option<String>> {
+        let key = format!(
+            "{}|{}|{}",
+            server
+                .machine_identifier
+                .clone()
+                .unwrap_or_else(|| server.base_url()),
+            session
+                .rating_key
+                .as_deref()
+                .unwrap_or(&session.media_title),
+            session.thumbnail.as_deref().unwrap_or_default()
+        );
+        if let Some(artwork) = self.entries.lock().unwrap().get(&key) {
+            return Ok(artwork.clone());
+        }
+        {
+            let mut failures = self.failures.lock().unwrap();
+            match failures.get(&key) {
+                Some(failed_at) if failed_at.elapsed() < self.failure_ttl => return Ok(None),
+                Some(_) => {
+                    failures.remove(&key);
+                }
+                None => {}
+            }
+        }
+
+        let result = tokio::time::timeout(
+            self.timeout,
+            self.inner.resolve(session, server, auth_token),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(AppError::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "artwork lookup timed out",
+            )))
+        });
+        let artwork = match result {
+            Ok(artwork) => artwork,
+            Err(e) => {
+                let mut failures = self.failures.lock().unwrap();
+                if failures.len() >= CACHE_CAPACITY {
+                    failures.clear();
+                }
+                failures.insert(key, Instant::now());
+                return Err(e);
+            }
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= CACHE_CAPACITY {
+            entries.clear();
+        }
+        entries.insert(key, artwork.clone());
+        Ok(artwork)
+    }
+}
+
+/// Posters from TMDB for movies and shows, covers from the Cover Art Archive for music.
+pub struct ExternalArtwork {
+    http_client: Client,
+    tmdb_api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TmdbPoster {
+    poster_path: Option<String>,
+}
+
+impl ExternalArtwork {
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> AppResult<Option<T>> {
+        // The query carries the TMDB key, keep it out of error messages and logs
+        let hide_url = |e: reqwest::Error| AppError::Network(e.without_url());
+        let response = self
+            .http_client
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(hide_url)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(hide_url)?;
+        Ok(Some(response.json().await.map_err(hide_url)?))
+    }
+
+    async fn tmdb_poster(&self, session: &Session) -> AppResult<Option<String>> {
+        let Some(api_key) = &self.tmdb_api_key else {
+            return Ok(None);
+        };
+        let key = [("api_key", api_key.as_str())];
+
+        let poster_path = match session.media_type {
+            MediaType::Movie => {
+                if let Some(id) = session.guid("tmdb") {
+                    self.get_json::<TmdbPoster>(&format!("{}/movie/{}", TMDB_API, id), &key)
+                        .await?
+                        .and_then(|movie| movie.poster_path)
+                } else if let Some(id) = session.guid("imdb") {
+                    self.tmdb_find(id, "imdb_id", api_key)
+                        .await?
+                        .and_then(|found| found.movie_results.into_iter().next())
+                        .and_then(|movie| movie.poster_path)
+                } else {
+                    None
+                }
+            }
+            // Episode stills are spoilers, the show's poster is shown instead
+            MediaType::Episode => {
+                let found = match (session.guid("tvdb"), session.guid("imdb")) {
+                    (Some(id), _) => self.tmdb_find(id, "tvdb_id", api_key).await?,
+                    (None, Some(id)) => self.tmdb_find(id, "imdb_id", api_key).await?,
+                    (None, None) => None,
+                };
+                let show_id = found
+                    .and_then(|found| found.tv_episode_results.into_iter().next())
+                    .map(|episode| episode.show_id);
+                match show_id {
+                    Some(show_id) => self
+                        .get_json::<TmdbPoster>(&format!("{}/tv/{}", TMDB_API, show_id), &key)
+                        .await?
+                        .and_then(|show| show.poster_path),
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        Ok(poster_path.map(|path| format!("{}{}", TMDB_IMAGES, path)))
+    }
+
+    async fn tmdb_find(
+        &self,
+        id: &str,
+        source: &str,
+        api_key: &str,
+    ) -> AppResult<Option<TmdbFind>> {
+        self.get_json(
+            &format!("{}/find/{}", TMDB_API, id),
+            &[("external_source", source), ("api_key", api_key)],
+        )
+        .await
+    }
+
+    async fn album_cover(&self, session: &Session) -> AppResult<Option<String>> {
+        #[derive(Deserialize)]
+        struct Recording {
+            #[serde(default)]
+            releases: Vec<Release>,
+        }
+
+        #[derive(Deserialize)]
+        struct Release {
+            id: String,
+        }
+
+        let Some(mbid) = session.guid("mbid") else {
+            return Ok(None);
+        };
+        let recording: Option<Recording> = self
+            .get_json(
+                &format!("{}/recording/{}", MUSICBRAINZ_API, mbid),
+                &[("inc", "releases"), ("fmt", "json")],
+            )
+            .await?;
+
+        // A recording can be on dozens of releases, the first few are enough
+        for release in recording
+            .map(|r| r.releases)
+            .unwrap_or_default()
+            .into_iter()
+            .take(5)
+        {
+            let url = format!("{}/release/{}/front-500", COVER_ART_ARCHIVE, release.id);
+            // Not every release has a cover uploaded
+            if self
+                .http_client
+                .head(&url)
+                .send()
+                .await?
+                .status()
+                .is_success()
+            {
+                return Ok(Some(url));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Deserialize)]
+struct TmdbFind {
+    #[serde(default)]
+    movie_results: Vec<TmdbPoster>,
+    #[serde(default)]
+    tv_episode_results: Vec<TmdbEpisode>,
+}
+
+#[derive(Deserialize)]
+struct TmdbEpisode {
+    show_id: u64,
+}
+
+#[async_trait]
+impl ArtworkResolver for ExternalArtwork {
+    async fn resolve(
+        &self,
+        session: &Session,
+        _server: &PlexServer,
+        _auth_token: &str,
+    ) -> AppResult<Option<String>> {
+        match session.media_type {
+            MediaType::Movie | MediaType::Episode => self.tmdb_poster(session).await,
+            MediaType::Track => self.album_cover(session).await,
+            MediaType::Unknown | MediaType::Idle => Ok(None),
+        }
+    }
+}
+
+/// Fetches the artwork from Plex and hands it to a user-run endpoint for hosting.
+///
+/// The image is POSTed as `image/jpeg`; the endpoint answers with the public URL,
+/// either as plain text or as JSON with a `url` field.
+pub struct UploadArtwork {
+    http_client: Client,
+    plex_client: Arc<dyn PlexClient>,
+    endpoint: String,
+    authorization: Option<String>,
+}
+
+#[async_trait]
+impl ArtworkResolver for UploadArtwork {
+    async fn resolve(
+        &self,
+        session: &Session,
+        server: &PlexServer,
+        auth_token: &str,
+    ) -> AppResult<Option<String>> {
+        let Some(path) = &session.thumbnail else {
+            return Ok(None);
+        };
+        let image = self
+            .plex_client
+            .get_artwork(server, auth_token, path)
+            .await?;
+
+        let mut request = self
+            .http_client
+            .post(&self.endpoint)
+            .header("Content-Type", "image/jpeg")
+            .body(image);
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let body = request.send().await?.error_for_status()?.text().await?;
+
+        #[derive(Deserialize)]
+        struct Uploaded {
+            url: String,
+        }
+
+        let url = match serde_json::from_str::<Uploaded>(&body) {
+            Ok(uploaded) => uploaded.url,
+            Err(_) => body.trim().to_string(),
+        };
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(AppError::Config(format!(
+                "Artwork upload endpoint answered without a URL: {}",
+                url
+            )));
+        }
+        Ok(Some(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::traits::MockArtworkResolver;
+
+    fn server() -> PlexServer {
+        PlexServer {
+            name: "Home".to_string(),
+            address: "192.168.1.10".to_string(),
+            port: 32400,
+            owned: true,
+            uri: None,
+            machine_identifier: Some("0a1b2c3d4e5f".to_string()),
+            connections: Vec::new(),
+        }
+    }
+
+    fn session(rating_key: &str) -> Session {
+        let mut session = Session::for_test(MediaType::Movie, "The Matrix");
+        session.rating_key = Some(rating_key.to_string());
+        session
+    }
+
+    fn cached(inner: MockArtworkResolver, failure_ttl: Duration) -> CachedArtwork {
+        CachedArtwork::with_limits(Box::new(inner), RESOLVE_TIMEOUT, failure_ttl)
+    }
+
+    #[tokio::test]
+    async fn resolves_each_item_once() {
+        let mut inner = MockArtworkResolver::new();
+        inner
+            .expect_resolve()
+            .withf(|session, _, _| session.rating_key.as_deref() == Some("1"))
+            .times(1)
+            .returning(|_, _, _| Ok(Some("https://example.com/1.jpg".to_string())));
+        inner
+            .expect_resolve()
+            .withf(|session, _, _| session.rating_key.as_deref() == Some("2"))
+            .times(1)
+            .returning(|_, _, _| Ok(None));
+        let cache = cached(inner, FAILURE_TTL);
+
+        for _ in 0..3 {
+            assert_eq!(
+                cache
+                    .resolve(&session("1"), &server(), "token")
+                    .await
+                    .unwrap(),
+                Some("https://example.com/1.jpg".to_string())
+            );
+            // Items without artwork are remembered too
+            assert_eq!(
+                cache
+                    .resolve(&session("2"), &server(), "token")
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_are_retried_after_the_ttl() {
+        let mut inner = MockArtworkResolver::new();
+        let mut seq = mockall::Sequence::new();
+        inner
+            .expect_resolve()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _, _| Err(AppError::PlexApi("unreachable".to_string())));
+        inner
+            .expect_resolve()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _, _| Ok(Some("https://example.com/1.jpg".to_string())));
+        let cache = cached(inner, Duration::ZERO);
+
+        assert!(cache
+            .resolve(&session("1"), &server(), "token")
+            .await
+            .is_err());
+        assert_eq!(
+            cache
+                .resolve(&session("1"), &server(), "token")
+                .await
+                .unwrap(),
+            Some("https://example.com/1.jpg".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn failures_are_not_retried_within_the_ttl() {
+        let mut inner = MockArtworkResolver::new();
+        inner
+            .expect_resolve()
+            .times(1)
+            .returning(|_, _, _| Err(AppError::PlexApi("unreachable".to_string())));
+        let cache = cached(inner, FAILURE_TTL);
+
+        assert!(cache
+            .resolve(&session("1"), &server(), "token")
+            .await
+            .is_err());
+        assert_eq!(
+            cache
+                .resolve(&session("1"), &server(), "token")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    struct HangingArtwork;
+
+    #[async_trait]
+    impl ArtworkResolver for HangingArtwork {
+        async fn resolve(
+            &self,
+            _session: &Session,
+            _server: &PlexServer,
+            _auth_token: &str,
+        ) -> AppResult<Option<String>> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_lookups_time_out() {
+        let cache = CachedArtwork::with_limits(
+            Box::new(HangingArtwork),
+            Duration::from_millis(10),
+            FAILURE_TTL,
+        );
+
+        assert!(cache
+            .resolve(&session("1"), &server(), "token")
+            .await
+            .is_err());
+        assert_eq!(
+            cache
+                .resolve(&session("1"), &server(), "token")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
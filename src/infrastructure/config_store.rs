@@ -1,18 +1,24 @@
 use crate::domain::models::{AppConfig, AppError, AppResult};
 use crate::domain::traits::ConfigStore;
+use crate::infrastructure::private_file::write_private;
+use crate::infrastructure::token_cipher::{self, TokenCipher};
 use directories::ProjectDirs;
 use std::fs;
 use std::path::PathBuf;
 
 pub struct FileConfigStore {
     path: Option<PathBuf>,
+    cipher: TokenCipher,
 }
 
 impl FileConfigStore {
     pub fn new() -> Self {
-        let path = ProjectDirs::from("com", "plex-discord-rpc", "plex-discord-rpc")
-            .map(|dirs| dirs.config_dir().join("config.json"));
-        Self { path }
+        let config_dir = ProjectDirs::from("com", "plex-discord-rpc", "plex-discord-rpc")
+            .map(|dirs| dirs.config_dir().to_path_buf());
+        Self {
+            path: config_dir.as_ref().map(|dir| dir.join("config.json")),
+            cipher: TokenCipher::new(config_dir.map(|dir| dir.join("token.key"))),
+        }
     }
 }
 
@@ -21,12 +27,65 @@ impl ConfigStore for FileConfigStore {
         if let Some(path) = &self.path {
             if path.exists() {
                 let data = fs::read_to_string(path)?;
-                let config: AppConfig = serde_json::from_str(&data)
+                let mut value: serde_json::Value = serde_json::from_str(&data)
+                    .map_err(|e| AppError::Config(format!("Failed to parse config: {}", e)))?;
+                let obj = value
+                    .as_object_mut()
+                    .ok_or_else(|| AppError::Config("Config is not a JSON object".into()))?;
+
+                let version = match obj.get("version") {
+                    None => 0,
+                    Some(v) => v
+                        .as_u64()
+                        .and_then(|v| u32::try_from(v).ok())
+                        .ok_or_else(|| AppError::Config(format!("Invalid config version {}", v)))?,
+                };
+                if version > CONFIG_VERSION {
+                    return Err(AppError::Config(format!(
+                        "Config version {} was written by a newer release, this one supports up to {}",
+                        version, CONFIG_VERSION
+                    )));
+                }
+                if version < CONFIG_VERSION {
+                    let backup = path.with_file_name(format!("config.v{}.json.bak", version));
+                    write_private(&backup, data.as_bytes())?;
+                    log::info!(
+                        "Migrating config from version {} to {}, backup saved to {}",
+                        version,
+                        CONFIG_VERSION,
+                        backup.display()
+                    );
+                    for migrate in &MIGRATIONS[version as usize..] {
+                        migrate(obj);
+                    }
+                    obj.insert("version".into(), CONFIG_VERSION.into());
+                }
+
+                let explicit_encryption = obj.contains_key("token_encryption");
+                let mut config: AppConfig = serde_json::from_value(value.clone())
                     .map_err(|e| AppError::Config(format!("Failed to parse config: {}", e)))?;
+                if version < CONFIG_VERSION {
+                    // Stored as migrated so the upgrade and backup only happen once
+                    let migrated = serde_json::to_string_pretty(&value).map_err(|e| {
+                        AppError::Config(format!("Failed to serialize config: {}", e))
+                    })?;
+                    write_private(path, migrated.as_bytes())?;
+                }
+                if let Some(stored) = config.auth_token.take() {
+                    // Without a setting keep the scheme the token was written with. An explicit
+                    // one wins, the token is rewritten with it on the next save.
+                    if !explicit_encryption {
+                        config.token_encryption = token_cipher::encryption_of(&stored);
+                    }
+                    config.auth_token = Some(self.cipher.decrypt(&stored)?);
+                }
                 return Ok(config);
             }
         }
-        Ok(AppConfig::default())
+        Ok(AppConfig {
+            version: CONFIG_VERSION,
+            ..AppConfig::default()
+        })
     }
 
     fn save(&self, config: &AppConfig) -> AppResult<()> {
@@ -34,12 +93,246 @@ impl ConfigStore for FileConfigStore {
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            let data = serde_json::to_string_pretty(config)
+            let mut stored = config.clone();
+            stored.version = CONFIG_VERSION;
+            // Plaintext configs from older versions are encrypted here the first time they're saved
+            if let Some(token) = &config.auth_token {
+                stored.auth_token = Some(self.cipher.encrypt(token, config.token_encryption)?);
+            }
+            let data = serde_json::to_string_pretty(&stored)
                 .map_err(|e| AppError::Config(format!("Failed to serialize config: {}", e)))?;
-            fs::write(path, data)?;
-            Ok(())
+            write_private(path, data.as_bytes())
         } else {
             Err(AppError::Config("Could not determine config path".into()))
         }
     }
+
+    fn reset(&self) -> AppResult<()> {
+        let Some(path) = &self.path else {
+            return Err(AppError::Config("Could not determine config path".into()));
+        };
+        if path.exists() {
+            let backup = path.with_file_name("config.json.bak");
+            fs::rename(path, &backup)?;
+            log::warn!("Moved unreadable config to {}", backup.display());
+        }
+        Ok(())
+    }
+}
+
+/// Layout upgrades in order, `MIGRATIONS[n]` turns a version `n` config into version `n + 1`.
+/// Configs without a `version` field are version 0.
+const MIGRATIONS: &[fn(&mut serde_json::Map<String, serde_json::Value>)] = &[upgrade_single_server];
+
+const CONFIG_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Version 0 → 1: older configs stored one server as flat `server_*` fields
+/// instead of a `servers` list.
+fn upgrade_single_server(obj: &mut serde_json::Map<String, serde_json::Value>) {
+    if obj.contains_key("servers") {
+        return;
+    }
+
+    let address = obj.remove("server_address");
+    let port = obj.remove("server_port");
+    let name = obj.remove("server_name");
+    let owned = obj.remove("is_owned");
+
+    let servers = match (address, port) {
+        (
+            Some(address @ serde_json::Value::String(_)),
+            Some(port @ serde_json::Value::Number(_)),
+        ) => {
+            vec![serde_json::json!({
+                "name": name.filter(|n| n.is_string()).unwrap_or_else(|| "Plex Server".into()),
+                "address": address,
+                "port": port,
+                "owned": owned.filter(|o| o.is_boolean()).unwrap_or(false.into()),
+            })]
+        }
+        _ => Vec::new(),
+    };
+    obj.insert("servers".into(), serde_json::Value::Array(servers));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::TokenEncryption;
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        store: FileConfigStore,
+    }
+
+    impl Fixture {
+        fn with_config(data: &str) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            fs::write(dir.path().join("config.json"), data).unwrap();
+            let store = FileConfigStore {
+                path: Some(dir.path().join("config.json")),
+                cipher: TokenCipher::new(Some(dir.path().join("token.key"))),
+            };
+            Self { dir, store }
+        }
+
+        fn read(&self, name: &str) -> Option<String> {
+            fs::read_to_string(self.dir.path().join(name)).ok()
+        }
+    }
+
+    #[test]
+    fn migrates_single_server_config() {
+        let original = r#"{
+            "auth_token": "plain-token",
+            "username": "alice",
+            "client_id": "0b6f2f7e-3c1d-4a8e-9f4b-2d7c5e1a8b90",
+            "server_address": "192.168.1.10",
+            "server_port": 32400,
+            "server_name": "Home",
+            "is_owned": true
+        }"#;
+        let fixture = Fixture::with_config(original);
+
+        let config = fixture.store.load().unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.auth_token.as_deref(), Some("plain-token"));
+        assert_eq!(config.servers.len(), 1);
+        assert_eq!(config.servers[0].name, "Home");
+        assert_eq!(config.servers[0].address, "192.168.1.10");
+        assert_eq!(config.servers[0].port, 32400);
+        assert!(config.servers[0].owned);
+
+        assert_eq!(
+            fixture.read("config.v0.json.bak").as_deref(),
+            Some(original)
+        );
+        let stored: serde_json::Value =
+            serde_json::from_str(&fixture.read("config.json").unwrap()).unwrap();
+        assert_eq!(stored["version"], CONFIG_VERSION);
+        assert!(stored.get("server_address").is_none());
+        // The next load finds the current version and doesn't migrate again
+        assert_eq!(fixture.store.load().unwrap().servers, config.servers);
+        assert_eq!(
+            fixture.read("config.v0.json.bak").as_deref(),
+            Some(original)
+        );
+    }
+
+    #[test]
+    fn migrates_unversioned_config_without_server() {
+        let original = r#"{"auth_token": "plain-token", "username": "alice", "client_id": ""}"#;
+        let fixture = Fixture::with_config(original);
+
+        let config = fixture.store.load().unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.auth_token.as_deref(), Some("plain-token"));
+        assert!(config.servers.is_empty());
+        assert_eq!(config.token_encryption, TokenEncryption::None);
+        assert_eq!(
+            fixture.read("config.v0.json.bak").as_deref(),
+            Some(original)
+        );
+    }
+
+    #[test]
+    fn rejects_config_from_newer_release() {
+        let original = format!(
+            r#"{{"version": {}, "username": "alice", "client_id": ""}}"#,
+            CONFIG_VERSION + 1
+        );
+        let fixture = Fixture::with_config(&original);
+
+        let error = fixture.store.load().unwrap_err();
+
+        assert!(error.to_string().contains("newer release"));
+        assert_eq!(fixture.read("config.json"), Some(original));
+    }
+
+    #[test]
+    fn unparseable_config_is_left_alone() {
+        let original = r#"{"username": "alice", "#;
+        let fixture = Fixture::with_config(original);
+
+        assert!(fixture.store.load().is_err());
+
+        assert_eq!(fixture.read("config.json").as_deref(), Some(original));
+        assert!(fixture.read("config.v0.json.bak").is_none());
+    }
+
+    #[test]
+    fn migrated_config_that_does_not_load_is_not_written() {
+        let original = r#"{"username": 42, "client_id": ""}"#;
+        let fixture = Fixture::with_config(original);
+
+        assert!(fixture.store.load().is_err());
+
+        assert_eq!(fixture.read("config.json").as_deref(), Some(original));
+        assert_eq!(
+            fixture.read("config.v0.json.bak").as_deref(),
+            Some(original)
+        );
+    }
+
+    #[test]
+    fn missing_config_loads_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileConfigStore {
+            path: Some(dir.path().join("config.json")),
+            cipher: TokenCipher::new(None),
+        };
+
+        let config = store.load().unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.servers.is_empty());
+    }
+
+    /// Rewrites `token_encryption` in the saved config, `None` removes it.
+    fn set_encryption(fixture: &Fixture, encryption: Option<&str>) {
+        let path = fixture.dir.path().join("config.json");
+        let mut stored: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let obj = stored.as_object_mut().unwrap();
+        match encryption {
+            Some(encryption) => obj.insert("token_encryption".into(), encryption.into()),
+            None => obj.remove("token_encryption"),
+        };
+        fs::write(&path, stored.to_string()).unwrap();
+    }
+
+    fn stored_token(fixture: &Fixture) -> String {
+        let stored: serde_json::Value =
+            serde_json::from_str(&fixture.read("config.json").unwrap()).unwrap();
+        stored["auth_token"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn explicit_token_encryption_wins_over_the_stored_token() {
+        let fixture =
+            Fixture::with_config(r#"{"client_id": "0b6f2f7e-3c1d-4a8e-9f4b-2d7c5e1a8b90"}"#);
+        let config = AppConfig {
+            auth_token: Some("plain-token".to_string()),
+            token_encryption: TokenEncryption::KeyFile,
+            ..fixture.store.load().unwrap()
+        };
+        fixture.store.save(&config).unwrap();
+        assert_ne!(stored_token(&fixture), "plain-token");
+
+        // Without the setting the scheme of the stored token is kept
+        set_encryption(&fixture, None);
+        let config = fixture.store.load().unwrap();
+        assert_eq!(config.token_encryption, TokenEncryption::KeyFile);
+        assert_eq!(config.auth_token.as_deref(), Some("plain-token"));
+
+        // Switching back to plaintext
+        set_encryption(&fixture, Some("none"));
+        let config = fixture.store.load().unwrap();
+        assert_eq!(config.token_encryption, TokenEncryption::None);
+        assert_eq!(config.auth_token.as_deref(), Some("plain-token"));
+        fixture.store.save(&config).unwrap();
+        assert_eq!(stored_token(&fixture), "plain-token");
+    }
 }
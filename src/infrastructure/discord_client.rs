@@ -1,7 +1,9 @@
+use crate::domain::links::PresenceButtons;
 use crate::domain::models::{AppError, AppResult, MediaType, PlayerState, Session};
+use crate::domain::template::PresenceTemplates;
 use crate::domain::traits::DiscordClient;
 use discord_presence_rs::{
-    activities::{Activity, ActivityType, Assets, StatusDisplayType, Timestamps},
+    activities::{Activity, ActivityType, Assets, Button, StatusDisplayType, Timestamps},
     discord_connection::Client as DiscordRpc,
 };
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -11,14 +13,18 @@ const MAX_RECONNECT_ATTEMPTS: u32 = 3;
 
 pub struct DiscordPresenceClient {
     client: Option<DiscordRpc>,
+    templates: PresenceTemplates,
+    buttons: PresenceButtons,
     last_connection_attempt: Option<SystemTime>,
     reconnect_delay: Duration,
 }
 
 impl DiscordPresenceClient {
-    pub fn new() -> Self {
+    pub fn new(templates: PresenceTemplates, buttons: PresenceButtons) -> Self {
         Self {
             client: None,
+            templates,
+            buttons,
             last_connection_attempt: None,
             reconnect_delay: Duration::from_secs(2),
         }
@@ -99,7 +105,7 @@ impl DiscordClient for DiscordPresenceClient {
         Ok(())
     }
 
-    fn update_presence(&mut self, session: &Session) -> AppResult<()> {
+    fn update_presence(&mut self, session: &Session, artwork: Option<String>) -> AppResult<()> {
         // Ensure we have a connection
         if self.client.is_none() {
             log::info!("No Discord client, attempting to connect...");
@@ -111,67 +117,27 @@ impl DiscordClient for DiscordPresenceClient {
             .unwrap_or(Duration::ZERO)
             .as_secs();
 
-        let (details, state, status_display, activity_type) = match session.media_type {
-            MediaType::Episode => {
-                let d = if let (Some(parent_idx), Some(idx)) =
-                    (session.media_parent_index, session.media_index)
-                {
-                    format!("S{} · E{} — {}", parent_idx, idx, session.media_title)
-                } else {
-                    session.media_title.clone()
-                };
-                (
-                    d,
-                    session.media_grandparent_title.clone().unwrap_or_default(),
-                    StatusDisplayType::State,
-                    ActivityType::Watching,
-                )
-            }
-            MediaType::Movie => (
-                session.media_title.clone(),
-                String::new(),
-                StatusDisplayType::Details,
-                ActivityType::Watching,
-            ),
-            MediaType::Track => (
-                session.media_title.clone(),
-                session.media_grandparent_title.clone().unwrap_or_default(), // Artist
-                StatusDisplayType::State,
-                ActivityType::Listening,
-            ),
-            _ => {
-                // Generic fallback matching GenericSessionRenderer.cs
-                // Details: Grandparent - Parent
-                // State: Title
-                let mut d = String::new();
-                if let Some(gp) = &session.media_grandparent_title {
-                    d.push_str(gp);
-                }
-                if let Some(p) = &session.media_parent_title {
-                    if !d.is_empty() {
-                        d.push_str(" - ");
-                    }
-                    d.push_str(p);
-                }
-
-                (
-                    d,
-                    session.media_title.clone(),
-                    StatusDisplayType::Name, // Reference uses Name for generic
-                    ActivityType::Watching,
-                )
-            }
+        let (status_display, activity_type) = match session.media_type {
+            MediaType::Episode => (StatusDisplayType::State, ActivityType::Watching),
+            MediaType::Movie => (StatusDisplayType::Details, ActivityType::Watching),
+            MediaType::Track => (StatusDisplayType::State, ActivityType::Listening),
+            // Generic fallback matching GenericSessionRenderer.cs
+            _ => (StatusDisplayType::Name, ActivityType::Watching),
         };
 
-        // Note: Reference uses session.Thumbnail URL for large image.
-        let large_image_url = session.thumbnail.clone().unwrap_or_else(|| "".to_string()); // Default to empty if no URL, image key fallback logic below
+        let format = self.templates.for_media_type(&session.media_type);
+        let details = format.details.render(session);
+        let state = format.state.render(session);
+        let large_text = format.large_text.render(session);
 
         let mut assets = Assets::new();
 
-        if !large_image_url.is_empty() {
-            // Use set_large_url for URLs as provided by the crate
-            assets = assets.set_large_url(large_image_url.clone());
-            assets = assets.set_large_text(session.media_title.clone());
+        // An asset key or a public URL from the artwork resolver, never the Plex URL
+        if let Some(artwork) = artwork.filter(|a| !a.is_empty()) {
+            assets = assets.set_large_image(artwork);
+            if !large_text.is_empty() {
+                assets = assets.set_large_text(large_text);
+            }
         }
 
         match session.player_state {
@@ -212,7 +178,18 @@ impl DiscordClient for DiscordPresenceClient {
             activity = activity.set_state(state);
         }
 
-        if session.player_state == PlayerState::Playing {
+        let buttons: Vec<Button> = self
+            .buttons
+            .for_session(session)
+            .into_iter()
+            .map(|(label, url)| Button::new().set_label(label.to_string()).set_url(url))
+            .collect();
+        if !buttons.is_empty() {
+            activity = activity.set_buttons(buttons);
+        }
+
+        // Without a duration there's no meaningful progress bar
+        if session.player_state == PlayerState::Playing && session.duration > 0 {
             let elapsed_secs = session.view_offset / 1000;
             let remaining_secs = (session.duration.saturating_sub(session.view_offset)) / 1000;
             let start = now.saturating_sub(elapsed_secs);
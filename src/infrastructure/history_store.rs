@@ -0,0 +1,66 @@
+use crate::domain::models::{AppError, AppResult, WatchHistoryEntry};
+use crate::domain::traits::HistoryStore;
+use directories::ProjectDirs;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Append-only history, one JSON entry per line.
+pub struct FileHistoryStore {
+    path: Option<PathBuf>,
+}
+
+impl FileHistoryStore {
+    pub fn new() -> Self {
+        let path = ProjectDirs::from("com", "plex-discord-rpc", "plex-discord-rpc")
+            .map(|dirs| dirs.data_dir().join("history.jsonl"));
+        Self { path }
+    }
+}
+
+impl HistoryStore for FileHistoryStore {
+    fn append(&self, entry: &WatchHistoryEntry) -> AppResult<()> {
+        let Some(path) = &self.path else {
+            return Err(AppError::Config("Could not determine history path".into()));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| AppError::Config(format!("Failed to serialize history: {}", e)))?;
+        line.push('\n');
+
+        let mut options = fs::OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        // One write per line, so a crash can at most cut off the last entry
+        options.open(path)?.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn load(&self) -> AppResult<Vec<WatchHistoryEntry>> {
+        let Some(path) = self.path.as_ref().filter(|p| p.exists()) else {
+            return Ok(Vec::new());
+        };
+
+        let data = fs::read_to_string(path)?;
+        Ok(data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(idx, line)| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    // One damaged line shouldn't hide the rest of the history
+                    log::warn!("Skipping history line {}: {}", idx + 1, e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
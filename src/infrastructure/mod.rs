@@ -1,3 +1,7 @@
+pub mod artwork_resolver;
 pub mod config_store;
 pub mod discord_client;
+pub mod history_store;
 pub mod plex_client;
+pub mod private_file;
+pub mod token_cipher;
@@ -3,11 +3,14 @@ use crate::domain::models::AppResult;
 use crate::domain::models::*;
 use crate::domain::traits::PlexClient;
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::Deserialize;
 use std::time::Duration;
 
 const PLEX_TV_API: &str = "https://plex.tv/api/v2";
+const CONNECTION_PROBE_TIMEOUT: Duration = Duration::from_secs(4);
+/// Artwork is scaled down by the server to fit this many pixels per side.
+const ARTWORK_SIZE: u32 = 512;
 
 pub struct ReqwestPlexClient {
     client_id: String,
@@ -66,34 +69,6 @@ impl ReqwestPlexClient {
             tokio::time::sleep(Duration::from_secs(INITIAL_DELAY * retries as u64)).await;
         }
     }
-
-    async fn get_username(&self, auth_token: &str) -> AppResult<String> {
-        #[derive(Deserialize)]
-        struct UserResponse {
-            username: String,
-        }
-
-        let request = self
-            .http_client
-            .get("https://plex.tv/api/v2/user")
-            .header("X-Plex-Token", auth_token)
-            .header("X-Plex-Client-Identifier", &self.client_id)
-            .header("X-Plex-Product", "Plex Discord RPC")
-            .header("X-Plex-Version", "1.0.0")
-            .header("Accept", "application/json");
-
-        let response = self.send_with_retry(request).await?;
-
-        if !response.status().is_success() {
-            return Err(AppError::PlexApi(format!(
-                "Failed to get username: {}",
-                response.status()
-            )));
-        }
-
-        let user: UserResponse = response.json().await.map_err(AppError::Network)?;
-        Ok(user.username)
-    }
 }
 
 #[async_trait]
@@ -142,7 +117,7 @@ impl PlexClient for ReqwestPlexClient {
 
         let request = self
             .http_client
-            .get(&format!("https://plex.tv/api/v2/pins/{}", pin_id))
+            .get(format!("https://plex.tv/api/v2/pins/{}", pin_id))
             .header("X-Plex-Client-Identifier", &self.client_id)
             .header("X-Plex-Product", "Plex Discord RPC")
             .header("X-Plex-Version", "1.0.0")
@@ -159,15 +134,109 @@ impl PlexClient for ReqwestPlexClient {
 
         let pin_check: PinCheckResponse = response.json().await.map_err(AppError::Network)?;
 
-        if let Some(token) = pin_check.auth_token {
-            let username = self.get_username(&token).await?;
-            Ok(Some(PlexAuth {
-                auth_token: token,
-                username,
-            }))
-        } else {
-            Ok(None)
+        match pin_check.auth_token {
+            Some(token) => Ok(Some(self.get_account(&token).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn sign_in_with_credentials(
+        &self,
+        login: &str,
+        password: &str,
+        verification_code: Option<String>,
+    ) -> AppResult<PlexAuth> {
+        #[derive(Deserialize)]
+        struct SignInResponse {
+            user: SignInUser,
+        }
+
+        #[derive(Deserialize)]
+        struct SignInUser {
+            #[serde(rename = "authToken")]
+            auth_token: String,
+            id: u64,
+            username: String,
+        }
+
+        #[derive(Deserialize)]
+        struct ErrorResponse {
+            error: String,
         }
+
+        // sign_in.json takes the 2FA code appended to the password
+        let password = match verification_code {
+            Some(code) => format!("{}{}", password, code),
+            None => password.to_string(),
+        };
+
+        let request = self
+            .http_client
+            .post("https://plex.tv/users/sign_in.json")
+            .header("X-Plex-Client-Identifier", &self.client_id)
+            .header("X-Plex-Product", "Plex Discord RPC")
+            .header("X-Plex-Version", "1.0.0")
+            .header("Accept", "application/json")
+            .form(&[
+                ("user[login]", login),
+                ("user[password]", password.as_str()),
+            ]);
+
+        // Sent once, a retry could repeat a used verification code or trip the lockout
+        let response = request.send().await.map_err(AppError::Network)?;
+        let status = response.status();
+
+        if !status.is_success() {
+            // Plex explains what went wrong, e.g. a missing verification code
+            let message = response
+                .json::<ErrorResponse>()
+                .await
+                .map(|e| e.error)
+                .unwrap_or_else(|_| format!("Sign in failed: {}", status));
+            return Err(AppError::Auth(message));
+        }
+
+        let sign_in: SignInResponse = response.json().await.map_err(AppError::Network)?;
+
+        Ok(PlexAuth {
+            auth_token: sign_in.user.auth_token,
+            username: sign_in.user.username,
+            user_id: sign_in.user.id,
+        })
+    }
+
+    async fn get_account(&self, auth_token: &str) -> AppResult<PlexAuth> {
+        #[derive(Deserialize)]
+        struct UserResponse {
+            id: u64,
+            username: String,
+        }
+
+        let request = self
+            .http_client
+            .get("https://plex.tv/api/v2/user")
+            .header("X-Plex-Token", auth_token)
+            .header("X-Plex-Client-Identifier", &self.client_id)
+            .header("X-Plex-Product", "Plex Discord RPC")
+            .header("X-Plex-Version", "1.0.0")
+            .header("Accept", "application/json");
+
+        let response = self.send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(
+                format!("Failed to get account: {}", response.status()),
+                response.status(),
+                true,
+            ));
+        }
+
+        let user: UserResponse = response.json().await.map_err(AppError::Network)?;
+        Ok(PlexAuth {
+            auth_token: auth_token.to_string(),
+            username: user.username,
+            user_id: user.id,
+        })
     }
 
     async fn get_servers(&self, auth_token: &str) -> AppResult<Vec<PlexServer>> {
@@ -175,19 +244,27 @@ impl PlexClient for ReqwestPlexClient {
         struct Resource {
             name: String,
             owned: bool,
+            #[serde(rename = "clientIdentifier", default)]
+            client_identifier: Option<String>,
+            #[serde(default)]
             connections: Vec<Connection>,
         }
 
-        #[derive(Deserialize, Clone)]
+        #[derive(Deserialize)]
         struct Connection {
+            #[serde(default)]
+            protocol: String,
             address: String,
             port: u16,
+            uri: String,
             local: bool,
+            #[serde(default)]
+            relay: bool,
         }
 
         let request = self
             .http_client
-            .get(&format!("{}/resources", PLEX_TV_API))
+            .get(format!("{}/resources", PLEX_TV_API))
             .header("X-Plex-Token", auth_token)
             .header("X-Plex-Client-Identifier", &self.client_id)
             .header("X-Plex-Product", "Plex Discord RPC")
@@ -198,10 +275,11 @@ impl PlexClient for ReqwestPlexClient {
         let response = self.send_with_retry(request).await?;
 
         if !response.status().is_success() {
-            return Err(AppError::PlexApi(format!(
-                "Failed to get servers: {}",
-                response.status()
-            )));
+            return Err(status_error(
+                format!("Failed to get servers: {}", response.status()),
+                response.status(),
+                true,
+            ));
         }
 
         let resources: Vec<Resource> = response.json().await.map_err(AppError::Network)?;
@@ -209,74 +287,121 @@ impl PlexClient for ReqwestPlexClient {
         let servers = resources
             .into_iter()
             .filter_map(|r| {
-                let connection = r
+                let connections: Vec<ServerConnection> = r
                     .connections
-                    .iter()
-                    .find(|c| c.local)
-                    .or_else(|| r.connections.first())
-                    .cloned()?;
+                    .into_iter()
+                    .map(|c| ServerConnection {
+                        protocol: c.protocol,
+                        address: c.address,
+                        port: c.port,
+                        uri: c.uri,
+                        local: c.local,
+                        relay: c.relay,
+                    })
+                    .collect();
+
+                // Until probed, assume the most preferred connection works
+                let connection = connections.iter().min_by_key(|c| c.preference())?.clone();
 
-                Some(PlexServer {
+                let server = PlexServer {
                     name: r.name,
-                    address: connection.address,
-                    port: connection.port,
+                    address: String::new(),
+                    port: 0,
                     owned: r.owned,
-                })
+                    uri: None,
+                    machine_identifier: r.client_identifier,
+                    connections,
+                };
+                Some(server.with_connection(&connection))
             })
             .collect();
 
         Ok(servers)
     }
 
-    async fn get_sessions(
-        &self,
-        server: &PlexServer,
-        auth_token: &str,
-        username: &str,
-    ) -> AppResult<Option<Session>> {
+    async fn get_players(&self, auth_token: &str) -> AppResult<Vec<PlayerInfo>> {
         #[derive(Deserialize)]
-        struct MediaContainer {
-            #[serde(rename = "Metadata", default)]
-            metadata: Vec<Metadata>,
-        }
-
-        #[derive(Deserialize)]
-        struct Metadata {
-            #[serde(rename = "type")]
-            media_type: String,
-            title: String,
+        struct Resource {
+            name: String,
+            #[serde(default)]
+            product: Option<String>,
             #[serde(default)]
-            index: Option<u32>,
-            #[serde(rename = "parentTitle", default)]
-            parent_title: Option<String>,
-            #[serde(rename = "parentIndex", default)]
-            parent_index: Option<u32>,
-            #[serde(rename = "grandparentTitle", default)]
-            grandparent_title: Option<String>,
-            duration: u64,
-            #[serde(rename = "viewOffset")]
-            view_offset: u64,
+            platform: Option<String>,
             #[serde(default)]
-            thumb: Option<String>,
-            #[serde(rename = "grandparentThumb", default)]
-            grandparent_thumb: Option<String>,
-            #[serde(rename = "Player")]
-            player: Player,
-            #[serde(rename = "User")]
-            user: User,
+            device: Option<String>,
+            #[serde(rename = "clientIdentifier")]
+            client_identifier: String,
+            // Comma separated, e.g. `client,player,pubsub-player`
+            #[serde(default)]
+            provides: String,
         }
 
-        #[derive(Deserialize)]
-        struct Player {
-            state: String,
+        let request = self
+            .http_client
+            .get(format!("{}/resources", PLEX_TV_API))
+            .header("X-Plex-Token", auth_token)
+            .header("X-Plex-Client-Identifier", &self.client_id)
+            .header("X-Plex-Product", "Plex Discord RPC")
+            .header("X-Plex-Version", "1.0.0")
+            .header("Accept", "application/json");
+
+        let response = self.send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(
+                format!("Failed to get players: {}", response.status()),
+                response.status(),
+                true,
+            ));
         }
 
-        #[derive(Deserialize)]
-        struct User {
-            title: String,
+        let resources: Vec<Resource> = response.json().await.map_err(AppError::Network)?;
+
+        Ok(resources
+            .into_iter()
+            .filter(|r| r.provides.split(',').any(|p| p == "player"))
+            .map(|r| PlayerInfo {
+                title: Some(r.name),
+                product: r.product,
+                platform: r.platform,
+                device: r.device,
+                machine_identifier: Some(r.client_identifier),
+            })
+            .collect())
+    }
+
+    async fn check_connection(&self, uri: &str, auth_token: &str, owned: bool) -> AppResult<()> {
+        let request = self
+            .http_client
+            .get(format!("{}/identity", uri.trim_end_matches('/')))
+            .header("X-Plex-Token", auth_token)
+            .header("X-Plex-Client-Identifier", &self.client_id)
+            .header("X-Plex-Product", "Plex Discord RPC")
+            .header("X-Plex-Version", "1.0.0")
+            .header("Accept", "application/json")
+            .timeout(CONNECTION_PROBE_TIMEOUT);
+
+        // No retries, an unreachable connection should fail fast
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(
+                format!("Connection check failed for {}: {}", uri, response.status()),
+                response.status(),
+                owned,
+            ));
         }
 
-        let url = format!("http://{}:{}/status/sessions", server.address, server.port);
+        Ok(())
+    }
+
+    async fn get_sessions(
+        &self,
+        server: &PlexServer,
+        auth_token: &str,
+        matcher: &SessionMatcher,
+    ) -> AppResult<Vec<Session>> {
+        let url = format!("{}/status/sessions", server.base_url());
 
         let request = self
             .http_client
@@ -285,90 +410,152 @@ impl PlexClient for ReqwestPlexClient {
             .header("X-Plex-Client-Identifier", &self.client_id)
             .header("X-Plex-Product", "Plex Discord RPC")
             .header("X-Plex-Version", "1.0.0")
-            .header("Accept", "application/json");
+            .header("Accept", "application/json")
+            .query(&[("includeGuids", "1")]);
 
         let response = self.send_with_retry(request).await?;
 
         if !response.status().is_success() {
-            // Often server might be unreachable or unauthorized, handle gracefully?
-            // For now return error so UI updates
-            return Err(AppError::PlexApi(format!(
-                "Failed to get sessions: {}",
-                response.status()
-            )));
+            // Non-admins of a shared server get 401 here, that's not a revoked token
+            return Err(status_error(
+                format!("Failed to get sessions: {}", response.status()),
+                response.status(),
+                server.owned,
+            ));
         }
 
-        #[derive(Deserialize)]
-        struct Response {
-            #[serde(rename = "MediaContainer")]
-            media_container: MediaContainer,
-        }
+        let data: MetadataResponse = response.json().await.map_err(AppError::Network)?;
+        Ok(parse_sessions(
+            data.media_container.metadata,
+            server,
+            matcher,
+        ))
+    }
 
-        let data: Response = response.json().await.map_err(AppError::Network)?;
+    async fn get_session_from_notification(
+        &self,
+        server: &PlexServer,
+        auth_token: &str,
+        notification: &PlaySessionStateNotification,
+    ) -> AppResult<Option<Session>> {
+        // `key` is already the full path, e.g. "/library/metadata/123"
+        let url = format!(
+            "{}/{}",
+            server.base_url(),
+            notification.key.trim_start_matches('/')
+        );
 
-        let user_sessions: Vec<_> = data
-            .media_container
-            .metadata
-            .into_iter()
-            .filter(|m| m.user.title == username)
-            .collect();
+        let request = self
+            .http_client
+            .get(&url)
+            .header("X-Plex-Token", auth_token)
+            .header("X-Plex-Client-Identifier", &self.client_id)
+            .header("X-Plex-Product", "Plex Discord RPC")
+            .header("X-Plex-Version", "1.0.0")
+            .header("Accept", "application/json")
+            .query(&[("includeGuids", "1")]);
 
-        let session = user_sessions
-            .iter()
-            .find(|m| m.player.state == "playing")
-            .or_else(|| user_sessions.iter().find(|m| m.player.state == "buffering"))
-            .or_else(|| user_sessions.iter().find(|m| m.player.state == "paused"));
+        let response = self.send_with_retry(request).await?;
 
-        if let Some(s) = session {
-            let thumbnail = s.thumb.as_ref().or(s.grandparent_thumb.as_ref()).map(|t| {
+        if !response.status().is_success() {
+            return Err(status_error(
                 format!(
-                    "http://{}:{}/{}?X-Plex-Token={}",
-                    server.address,
-                    server.port,
-                    t.trim_start_matches('/'),
-                    auth_token
-                )
-            });
-
-            Ok(Some(Session {
-                media_title: s.title.clone(),
-                media_index: s.index,
-                media_parent_title: s.parent_title.clone(),
-                media_parent_index: s.parent_index,
-                media_grandparent_title: s.grandparent_title.clone(),
-                player_state: match s.player.state.as_str() {
-                    "playing" => PlayerState::Playing,
-                    "paused" => PlayerState::Paused,
-                    "buffering" => PlayerState::Buffering,
-                    _ => PlayerState::Idle,
-                },
-                media_type: match s.media_type.as_str() {
-                    "episode" => MediaType::Episode,
-                    "movie" => MediaType::Movie,
-                    "track" => MediaType::Track,
-                    _ => MediaType::Unknown,
-                },
-                duration: s.duration,
-                view_offset: s.view_offset,
-                thumbnail,
-            }))
-        } else {
-            Ok(None)
+                    "Failed to get metadata for {}: {}",
+                    notification.key,
+                    response.status()
+                ),
+                response.status(),
+                server.owned,
+            ));
+        }
+
+        let data: MetadataResponse = response.json().await.map_err(AppError::Network)?;
+
+        let Some(item) = data.media_container.metadata.into_iter().next() else {
+            return Ok(None);
+        };
+        let media = match serde_json::from_value::<MediaMetadata>(item) {
+            Ok(media) => media,
+            Err(e) => {
+                log::warn!(
+                    "Skipping {} that couldn't be parsed: {}",
+                    notification.key,
+                    e
+                );
+                return Ok(None);
+            }
+        };
+
+        // Playback state comes from the notification, the metadata has none
+        let mut session = media.into_session(
+            server,
+            Some(notification.session_key.clone()),
+            notification.state.clone(),
+            notification.view_offset,
+        );
+        if session.rating_key.is_none() {
+            session.rating_key = notification.rating_key.clone();
         }
+        session.player = notification.player();
+
+        Ok(Some(session))
+    }
+
+    async fn get_artwork(
+        &self,
+        server: &PlexServer,
+        auth_token: &str,
+        path: &str,
+    ) -> AppResult<Vec<u8>> {
+        let url = format!("{}/photo/:/transcode", server.base_url());
+        let size = ARTWORK_SIZE.to_string();
+
+        let request = self
+            .http_client
+            .get(&url)
+            .header("X-Plex-Token", auth_token)
+            .header("X-Plex-Client-Identifier", &self.client_id)
+            .header("X-Plex-Product", "Plex Discord RPC")
+            .header("X-Plex-Version", "1.0.0")
+            .query(&[
+                ("url", path),
+                ("width", size.as_str()),
+                ("height", size.as_str()),
+                ("minSize", "1"),
+                ("format", "jpeg"),
+            ]);
+
+        let response = self.send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(
+                format!("Failed to get artwork {}: {}", path, response.status()),
+                response.status(),
+                server.owned,
+            ));
+        }
+
+        Ok(response.bytes().await.map_err(AppError::Network)?.to_vec())
     }
 
     async fn listen_for_notifications(
         &self,
         server: &PlexServer,
         auth_token: &str,
-        _username: &str, // Just notification trigger, application layer will refetch/filter
-    ) -> AppResult<tokio::sync::mpsc::Receiver<()>> {
+    ) -> AppResult<tokio::sync::mpsc::Receiver<PlexNotification>> {
         use futures_util::StreamExt;
         use tokio_tungstenite::connect_async;
+        use tokio_tungstenite::tungstenite::Message;
 
+        // Same scheme as the chosen connection, plex.direct URIs need TLS
+        let base_url = server.base_url();
+        let ws_base = match base_url.strip_prefix("https://") {
+            Some(rest) => format!("wss://{}", rest),
+            None => format!("ws://{}", base_url.trim_start_matches("http://")),
+        };
         let ws_url = format!(
-            "ws://{}:{}/:/websockets/notifications?X-Plex-Token={}",
-            server.address, server.port, auth_token
+            "{}/:/websockets/notifications?X-Plex-Token={}",
+            ws_base, auth_token
         );
 
         // Connect
@@ -382,24 +569,24 @@ impl PlexClient for ReqwestPlexClient {
             let (_, mut read) = ws_stream.split();
 
             while let Some(msg) = read.next().await {
-                if let Ok(msg) = msg {
-                    if msg.is_text() || msg.is_binary() {
-                        // Parse? For now just signal "something happened"
-                        // In shared server mode, any notification is worth checking session status
-                        // because shared server sessions endpoint might rely on notifications or
-                        // effectively we just want to know when state changes.
-                        //
-                        // Real implementation would parse `NotificationContainer` to filter "playing" events.
-                        // Given "reliable" request, let's just debounce signal in monitor loop and trigger session fetch.
-                        //
-                        // If we send signal on every message, we might spam.
-                        // Let's assume sending signal is cheap.
-                        if tx.send(()).await.is_err() {
-                            break;
-                        }
-                    }
-                } else {
+                let Ok(msg) = msg else {
                     break;
+                };
+
+                let text = match msg {
+                    Message::Text(text) => text.to_string(),
+                    Message::Binary(data) => match String::from_utf8(data.to_vec()) {
+                        Ok(text) => text,
+                        Err(_) => continue,
+                    },
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+
+                for notification in parse_notification(&text) {
+                    if tx.send(notification).await.is_err() {
+                        return;
+                    }
                 }
             }
         });
@@ -407,3 +594,575 @@ impl PlexClient for ReqwestPlexClient {
         Ok(rx)
     }
 }
+
+#[derive(Deserialize)]
+struct MetadataResponse {
+    #[serde(rename = "MediaContainer")]
+    media_container: MetadataContainer,
+}
+
+/// Items are kept as raw JSON and parsed one by one, so one odd item doesn't
+/// fail the whole response.
+#[derive(Deserialize)]
+struct MetadataContainer {
+    #[serde(rename = "Metadata", default)]
+    metadata: Vec<serde_json::Value>,
+}
+
+/// An item of `/status/sessions`.
+#[derive(Deserialize)]
+struct SessionMetadata {
+    #[serde(
+        rename = "sessionKey",
+        default,
+        deserialize_with = "deserialize_optional_id"
+    )]
+    session_key: Option<String>,
+    // Missing for live TV and some trailers
+    #[serde(rename = "viewOffset", default)]
+    view_offset: u64,
+    #[serde(rename = "Player", default)]
+    player: Option<SessionPlayer>,
+    #[serde(rename = "User", default)]
+    user: Option<SessionUser>,
+    #[serde(flatten)]
+    media: MediaMetadata,
+}
+
+#[derive(Deserialize)]
+struct SessionPlayer {
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    product: Option<String>,
+    #[serde(default)]
+    platform: Option<String>,
+    #[serde(default)]
+    device: Option<String>,
+    #[serde(rename = "machineIdentifier", default)]
+    machine_identifier: Option<String>,
+}
+
+impl SessionPlayer {
+    fn info(&self) -> PlayerInfo {
+        PlayerInfo {
+            title: self.title.clone(),
+            product: self.product.clone(),
+            platform: self.platform.clone(),
+            device: self.device.clone(),
+            machine_identifier: self.machine_identifier.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SessionUser {
+    // A string in JSON responses, like the other IDs
+    #[serde(default, deserialize_with = "deserialize_optional_id")]
+    id: Option<String>,
+    #[serde(default)]
+    title: String,
+}
+
+/// Sessions of the tracked users and players among `/status/sessions` items.
+/// Items that can't be parsed are logged and skipped.
+fn parse_sessions(
+    items: Vec<serde_json::Value>,
+    server: &PlexServer,
+    matcher: &SessionMatcher,
+) -> Vec<Session> {
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let kind = item
+                .get("type")
+                .and_then(|kind| kind.as_str())
+                .unwrap_or("item")
+                .to_string();
+            match serde_json::from_value::<SessionMetadata>(item) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    log::warn!("Skipping {} session that couldn't be parsed: {}", kind, e);
+                    None
+                }
+            }
+        })
+        .filter_map(|m| {
+            // Without a user or player there's no telling whose session it is
+            let (user, player) = (m.user.as_ref()?, m.player.as_ref()?);
+            let id = user.id.as_deref().and_then(|id| id.parse().ok());
+            let info = player.info();
+            if !matcher.matches_user(server, id, &user.title)
+                || !matcher.matches_player(Some(&info))
+            {
+                return None;
+            }
+            let state = parse_player_state(&player.state);
+            let mut session = m
+                .media
+                .into_session(server, m.session_key, state, m.view_offset);
+            session.player = Some(info);
+            Some(session)
+        })
+        .collect()
+}
+
+/// Media fields shared by `/status/sessions` and `/library/metadata` items.
+#[derive(Deserialize)]
+struct MediaMetadata {
+    #[serde(
+        rename = "ratingKey",
+        default,
+        deserialize_with = "deserialize_optional_id"
+    )]
+    rating_key: Option<String>,
+    #[serde(rename = "type", default)]
+    media_type: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    index: Option<u32>,
+    #[serde(rename = "parentTitle", default)]
+    parent_title: Option<String>,
+    #[serde(rename = "parentIndex", default)]
+    parent_index: Option<u32>,
+    #[serde(rename = "grandparentTitle", default)]
+    grandparent_title: Option<String>,
+    #[serde(default)]
+    duration: u64,
+    #[serde(default)]
+    thumb: Option<String>,
+    #[serde(rename = "grandparentThumb", default)]
+    grandparent_thumb: Option<String>,
+    #[serde(default)]
+    year: Option<u32>,
+    #[serde(rename = "librarySectionTitle", default)]
+    library_section_title: Option<String>,
+    // A string in session responses but a number in metadata responses
+    #[serde(
+        rename = "librarySectionID",
+        default,
+        deserialize_with = "deserialize_optional_id"
+    )]
+    library_section_id: Option<String>,
+    #[serde(rename = "Guid", default)]
+    guids: Vec<GuidEntry>,
+    #[serde(rename = "Genre", default)]
+    genres: Vec<TagEntry>,
+    #[serde(rename = "contentRating", default)]
+    content_rating: Option<String>,
+    #[serde(default)]
+    studio: Option<String>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(rename = "originallyAvailableAt", default)]
+    originally_available_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GuidEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    tag: String,
+}
+
+impl MediaMetadata {
+    fn into_session(
+        self,
+        server: &PlexServer,
+        session_key: Option<String>,
+        player_state: PlayerState,
+        view_offset: u64,
+    ) -> Session {
+        let thumbnail = self.thumb.or(self.grandparent_thumb);
+
+        Session {
+            session_key,
+            rating_key: self.rating_key,
+            media_title: self.title,
+            media_index: self.index,
+            media_parent_title: self.parent_title,
+            media_parent_index: self.parent_index,
+            media_grandparent_title: self.grandparent_title,
+            player_state,
+            media_type: parse_media_type(&self.media_type),
+            duration: self.duration,
+            view_offset,
+            thumbnail,
+            year: self.year,
+            library_section_id: self.library_section_id,
+            library_section_title: self.library_section_title,
+            guids: self.guids.into_iter().map(|g| g.id).collect(),
+            genres: self.genres.into_iter().map(|g| g.tag).collect(),
+            content_rating: self.content_rating,
+            studio: self.studio,
+            summary: self.summary,
+            originally_available_at: self.originally_available_at,
+            server_id: server.machine_identifier.clone(),
+            player: None,
+        }
+    }
+}
+
+fn deserialize_optional_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Text(String),
+        Number(u64),
+    }
+
+    Ok(Option::<Id>::deserialize(deserializer)?.map(|id| match id {
+        Id::Text(text) => text,
+        Id::Number(number) => number.to_string(),
+    }))
+}
+
+fn parse_player_state(state: &str) -> PlayerState {
+    match state {
+        "playing" => PlayerState::Playing,
+        "paused" => PlayerState::Paused,
+        "buffering" => PlayerState::Buffering,
+        _ => PlayerState::Idle,
+    }
+}
+
+/// Maps a failed response to an error. A 401 from plex.tv or from an owned server
+/// means the token itself was revoked or expired, anywhere else it may just be
+/// missing permissions.
+fn status_error(message: String, status: StatusCode, token_is_authoritative: bool) -> AppError {
+    if status == StatusCode::UNAUTHORIZED && token_is_authoritative {
+        AppError::Unauthorized(message)
+    } else {
+        AppError::PlexApi(message)
+    }
+}
+
+fn parse_media_type(media_type: &str) -> MediaType {
+    match media_type {
+        "episode" => MediaType::Episode,
+        "movie" => MediaType::Movie,
+        "track" => MediaType::Track,
+        _ => MediaType::Unknown,
+    }
+}
+
+/// Parses one WebSocket frame. A "playing" container can carry several sessions.
+fn parse_notification(text: &str) -> Vec<PlexNotification> {
+    #[derive(Deserialize)]
+    struct Message {
+        #[serde(rename = "NotificationContainer")]
+        container: NotificationContainer,
+    }
+
+    #[derive(Deserialize)]
+    struct NotificationContainer {
+        #[serde(rename = "type")]
+        kind: String,
+        #[serde(rename = "PlaySessionStateNotification", default)]
+        play_session_state: Vec<PlaySessionState>,
+    }
+
+    #[derive(Deserialize)]
+    struct PlaySessionState {
+        #[serde(rename = "sessionKey")]
+        session_key: String,
+        key: String,
+        #[serde(rename = "ratingKey", default)]
+        rating_key: Option<String>,
+        #[serde(rename = "clientIdentifier", default)]
+        client_identifier: Option<String>,
+        state: String,
+        #[serde(rename = "viewOffset", default)]
+        view_offset: u64,
+    }
+
+    let message: Message = match serde_json::from_str(text) {
+        Ok(m) => m,
+        Err(e) => {
+            log::debug!("Ignoring unparseable Plex notification: {}", e);
+            return Vec::new();
+        }
+    };
+
+    if message.container.kind != "playing" {
+        return vec![PlexNotification::Other(message.container.kind)];
+    }
+
+    message
+        .container
+        .play_session_state
+        .into_iter()
+        .map(|n| {
+            PlexNotification::PlaySessionState(PlaySessionStateNotification {
+                session_key: n.session_key,
+                key: n.key,
+                rating_key: n.rating_key,
+                client_identifier: n.client_identifier,
+                state: parse_player_state(&n.state),
+                view_offset: n.view_offset,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> PlexServer {
+        PlexServer {
+            name: "Home".to_string(),
+            address: "192.168.1.10".to_string(),
+            port: 32400,
+            owned: true,
+            uri: None,
+            machine_identifier: Some("0a1b2c3d4e5f".to_string()),
+            connections: Vec::new(),
+        }
+    }
+
+    fn matcher() -> SessionMatcher {
+        SessionMatcher {
+            account_id: Some(12345678),
+            username: "alice".to_string(),
+            additional: AdditionalUsers::default(),
+            devices: DeviceFilter::default(),
+        }
+    }
+
+    fn sessions(fixture: &str) -> Vec<Session> {
+        let data: MetadataResponse = serde_json::from_str(fixture).unwrap();
+        parse_sessions(data.media_container.metadata, &server(), &matcher())
+    }
+
+    fn only_session(fixture: &str) -> Session {
+        let mut sessions = sessions(fixture);
+        assert_eq!(sessions.len(), 1);
+        sessions.remove(0)
+    }
+
+    #[test]
+    fn parses_movie() {
+        let session = only_session(include_str!("../../tests/fixtures/plex/movie.json"));
+        assert_eq!(session.media_type, MediaType::Movie);
+        assert_eq!(session.media_title, "The Shawshank Redemption");
+        assert_eq!(session.session_key.as_deref(), Some("37"));
+        assert_eq!(session.rating_key.as_deref(), Some("4821"));
+        assert_eq!(session.duration, 8_520_000);
+        assert_eq!(session.view_offset, 1_843_000);
+        assert_eq!(session.player_state, PlayerState::Playing);
+        assert_eq!(session.year, Some(1994));
+        assert_eq!(session.content_rating.as_deref(), Some("R"));
+        assert_eq!(session.library_section_id.as_deref(), Some("1"));
+        assert_eq!(
+            session.guids,
+            ["imdb://tt0111161", "tmdb://278", "tvdb://190"]
+        );
+        assert_eq!(session.genres, ["Drama", "Crime"]);
+        assert_eq!(session.server_id.as_deref(), Some("0a1b2c3d4e5f"));
+        let player = session.player.unwrap();
+        assert_eq!(player.title.as_deref(), Some("Living Room Xbox"));
+        assert_eq!(player.product.as_deref(), Some("Plex for Xbox"));
+        assert_eq!(player.platform.as_deref(), Some("Xbox"));
+    }
+
+    #[test]
+    fn parses_episode() {
+        let session = only_session(include_str!("../../tests/fixtures/plex/episode.json"));
+        assert_eq!(session.media_type, MediaType::Episode);
+        assert_eq!(session.media_title, "Negro y Azul");
+        assert_eq!(
+            session.media_grandparent_title.as_deref(),
+            Some("Breaking Bad")
+        );
+        assert_eq!(session.media_parent_index, Some(2));
+        assert_eq!(session.media_index, Some(7));
+        assert_eq!(session.player_state, PlayerState::Paused);
+        assert_eq!(session.view_offset, 912_000);
+    }
+
+    #[test]
+    fn parses_track() {
+        let session = only_session(include_str!("../../tests/fixtures/plex/track.json"));
+        assert_eq!(session.media_type, MediaType::Track);
+        assert_eq!(session.media_title, "Bohemian Rhapsody");
+        assert_eq!(
+            session.media_parent_title.as_deref(),
+            Some("A Night at the Opera")
+        );
+        assert_eq!(session.media_grandparent_title.as_deref(), Some("Queen"));
+        assert_eq!(session.duration, 354_000);
+        assert_eq!(
+            session.player.and_then(|p| p.product).as_deref(),
+            Some("Plexamp")
+        );
+    }
+
+    #[test]
+    fn parses_live_tv_without_duration_or_rating_key() {
+        let session = only_session(include_str!("../../tests/fixtures/plex/live_tv.json"));
+        assert_eq!(session.media_type, MediaType::Episode);
+        assert_eq!(
+            session.media_grandparent_title.as_deref(),
+            Some("NBC Nightly News")
+        );
+        assert_eq!(session.rating_key, None);
+        assert_eq!(session.duration, 0);
+        assert_eq!(session.view_offset, 0);
+        assert_eq!(session.player_state, PlayerState::Playing);
+    }
+
+    #[test]
+    fn parses_clip() {
+        let session = only_session(include_str!("../../tests/fixtures/plex/clip.json"));
+        assert_eq!(session.media_type, MediaType::Unknown);
+        assert_eq!(session.media_title, "Dune: Part Two");
+        assert_eq!(session.duration, 0);
+        assert_eq!(session.player_state, PlayerState::Buffering);
+    }
+
+    #[test]
+    fn parses_photo() {
+        let session = only_session(include_str!("../../tests/fixtures/plex/photo.json"));
+        assert_eq!(session.media_type, MediaType::Unknown);
+        assert_eq!(session.media_title, "IMG_4021");
+        assert_eq!(session.media_parent_title.as_deref(), Some("Summer 2023"));
+        assert_eq!(session.duration, 0);
+    }
+
+    #[test]
+    fn skips_items_that_fail_to_parse_or_belong_to_others() {
+        let sessions = sessions(include_str!("../../tests/fixtures/plex/mixed.json"));
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].media_title, "The Matrix");
+        assert_eq!(sessions[0].player_state, PlayerState::Paused);
+    }
+
+    #[test]
+    fn parses_library_metadata_with_numeric_section_id() {
+        let data: MetadataResponse = serde_json::from_str(include_str!(
+            "../../tests/fixtures/plex/library_metadata.json"
+        ))
+        .unwrap();
+        let item = data.media_container.metadata.into_iter().next().unwrap();
+        let session = serde_json::from_value::<MediaMetadata>(item)
+            .unwrap()
+            .into_session(&server(), Some("37".to_string()), PlayerState::Playing, 0);
+        assert_eq!(session.media_type, MediaType::Movie);
+        assert_eq!(session.library_section_id.as_deref(), Some("1"));
+        assert_eq!(session.genres, ["Drama"]);
+    }
+
+    /// Answers every request on a local port with `status` and returns its base URL.
+    async fn respond_with(status: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_only_unauthorized_on_owned_servers() {
+        let uri = respond_with("401 Unauthorized").await;
+        let client = ReqwestPlexClient::new("test".to_string()).unwrap();
+
+        let shared = client.check_connection(&uri, "token", false).await;
+        assert!(matches!(shared, Err(AppError::PlexApi(_))), "{:?}", shared);
+
+        let owned = client.check_connection(&uri, "token", true).await;
+        assert!(
+            matches!(owned, Err(AppError::Unauthorized(_))),
+            "{:?}",
+            owned
+        );
+    }
+
+    #[test]
+    fn parses_playing_notifications() {
+        let notifications = parse_notification(include_str!(
+            "../../tests/fixtures/plex/notification_playing.json"
+        ));
+
+        assert_eq!(
+            notifications,
+            [
+                PlexNotification::PlaySessionState(PlaySessionStateNotification {
+                    session_key: "37".to_string(),
+                    key: "/library/metadata/4821".to_string(),
+                    rating_key: Some("4821".to_string()),
+                    client_identifier: Some("a7b3c9d1e5f2".to_string()),
+                    state: PlayerState::Playing,
+                    view_offset: 1_843_000,
+                }),
+                // Stopped sessions read as idle
+                PlexNotification::PlaySessionState(PlaySessionStateNotification {
+                    session_key: "38".to_string(),
+                    key: "/library/metadata/9120".to_string(),
+                    rating_key: Some("9120".to_string()),
+                    client_identifier: Some("f0e1d2c3b4a5".to_string()),
+                    state: PlayerState::Idle,
+                    view_offset: 0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_notification_types_are_passed_on_by_type() {
+        let notifications = parse_notification(include_str!(
+            "../../tests/fixtures/plex/notification_activity.json"
+        ));
+        assert_eq!(
+            notifications,
+            [PlexNotification::Other("activity".to_string())]
+        );
+
+        let timeline = r#"{"NotificationContainer":{"type":"timeline","size":0}}"#;
+        assert_eq!(
+            parse_notification(timeline),
+            [PlexNotification::Other("timeline".to_string())]
+        );
+    }
+
+    #[test]
+    fn malformed_notifications_are_ignored() {
+        let truncated = &include_str!("../../tests/fixtures/plex/notification_playing.json")[..200];
+        for text in [
+            "",
+            "not json",
+            truncated,
+            "{}",
+            r#"{"NotificationContainer":{"size":1}}"#,
+            // A session without its state
+            r#"{"NotificationContainer":{"type":"playing","PlaySessionStateNotification":[{"sessionKey":"37","key":"/library/metadata/4821"}]}}"#,
+            r#"{"NotificationContainer":{"type":"playing","PlaySessionStateNotification":{"sessionKey":"37"}}}"#,
+        ] {
+            assert_eq!(parse_notification(text), [], "{}", text);
+        }
+
+        // A playing container without sessions has nothing to report
+        let empty = r#"{"NotificationContainer":{"type":"playing","size":0}}"#;
+        assert_eq!(parse_notification(empty), []);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::domain::models::AppResult;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes through a temporary file so a crash never leaves a half-written file,
+/// readable only by the current user where the platform supports it.
+pub fn write_private(path: &Path, data: &[u8]) -> AppResult<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp_path)?;
+    // `mode` only applies to newly created files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "old").unwrap();
+
+        write_private(&path, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.path().join("config.tmp").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restricts_existing_files_to_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        // A leftover temporary file from a crash keeps its mode when reopened
+        fs::write(dir.path().join("config.tmp"), "").unwrap();
+        fs::set_permissions(
+            dir.path().join("config.tmp"),
+            fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        write_private(&path, b"new").unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
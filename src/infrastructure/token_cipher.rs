@@ -0,0 +1,294 @@
+use crate::domain::models::{AppError, AppResult, TokenEncryption};
+use crate::infrastructure::private_file::write_private;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::Sha256;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PASSPHRASE_ENV: &str = "PLEX_DISCORD_RPC_PASSPHRASE";
+
+/// Encrypted tokens are stored as `enc:v1:{scheme}:{base64}`.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const KEY_FILE_SCHEME: &str = "key";
+const PASSPHRASE_SCHEME: &str = "passphrase";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 210_000;
+
+/// Scheme an already encrypted value was written with, so a later save can keep it.
+pub fn encryption_of(value: &str) -> TokenEncryption {
+    match value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .and_then(|v| v.split_once(':'))
+    {
+        Some((KEY_FILE_SCHEME, _)) => TokenEncryption::KeyFile,
+        Some((PASSPHRASE_SCHEME, _)) => TokenEncryption::Passphrase,
+        _ => TokenEncryption::None,
+    }
+}
+
+/// Encrypts the auth token with ChaCha20-Poly1305, keyed by a key file or a passphrase.
+pub struct TokenCipher {
+    key_path: Option<PathBuf>,
+}
+
+impl TokenCipher {
+    pub fn new(key_path: Option<PathBuf>) -> Self {
+        Self { key_path }
+    }
+
+    pub fn encrypt(&self, token: &str, encryption: TokenEncryption) -> AppResult<String> {
+        let (scheme, prefix, key) = match encryption {
+            TokenEncryption::None => return Ok(token.to_string()),
+            TokenEncryption::KeyFile => (KEY_FILE_SCHEME, Vec::new(), self.load_or_create_key()?),
+            TokenEncryption::Passphrase => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let key = derive_key(&passphrase()?, &salt);
+                (PASSPHRASE_SCHEME, salt.to_vec(), key)
+            }
+        };
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&key)
+            .encrypt(&nonce, token.as_bytes())
+            .map_err(|_| AppError::Config("Failed to encrypt auth token".into()))?;
+
+        let mut payload = prefix;
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{}{}:{}",
+            ENCRYPTED_PREFIX,
+            scheme,
+            BASE64.encode(payload)
+        ))
+    }
+
+    /// Returns plaintext values unchanged, so configs from before encryption still load.
+    pub fn decrypt(&self, value: &str) -> AppResult<String> {
+        let Some(rest) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(value.to_string());
+        };
+        let (scheme, encoded) = rest
+            .split_once(':')
+            .ok_or_else(|| AppError::Config("Malformed encrypted auth token".into()))?;
+        let payload = BASE64
+            .decode(encoded)
+            .map_err(|e| AppError::Config(format!("Malformed encrypted auth token: {}", e)))?;
+
+        let (key, payload) = match scheme {
+            KEY_FILE_SCHEME => (self.load_key()?, payload.as_slice()),
+            PASSPHRASE_SCHEME if payload.len() > SALT_LEN => {
+                let (salt, rest) = payload.split_at(SALT_LEN);
+                (derive_key(&passphrase()?, salt), rest)
+            }
+            _ => {
+                return Err(AppError::Config(format!(
+                    "Unsupported auth token encryption '{}'",
+                    scheme
+                )))
+            }
+        };
+
+        if payload.len() <= NONCE_LEN {
+            return Err(AppError::Config("Malformed encrypted auth token".into()));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = ChaCha20Poly1305::new(&key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                AppError::Config(
+                    "Could not decrypt the auth token, the key or passphrase is wrong".into(),
+                )
+            })?;
+
+        String::from_utf8(plaintext)
+            .map_err(|_| AppError::Config("Decrypted auth token is not valid text".into()))
+    }
+
+    fn key_path(&self) -> AppResult<&Path> {
+        self.key_path
+            .as_deref()
+            .ok_or_else(|| AppError::Config("Could not determine key file path".into()))
+    }
+
+    fn load_key(&self) -> AppResult<Key> {
+        let path = self.key_path()?;
+        let data = fs::read_to_string(path).map_err(|e| {
+            AppError::Config(format!("Failed to read key file {}: {}", path.display(), e))
+        })?;
+        let bytes = BASE64
+            .decode(data.trim())
+            .ok()
+            .filter(|b| b.len() == KEY_LEN)
+            .ok_or_else(|| {
+                AppError::Config(format!("Key file {} is not a valid key", path.display()))
+            })?;
+        Ok(*Key::from_slice(&bytes))
+    }
+
+    fn load_or_create_key(&self) -> AppResult<Key> {
+        let path = self.key_path()?;
+        if path.exists() {
+            return self.load_key();
+        }
+
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_private(path, BASE64.encode(key).as_bytes())?;
+        log::info!("Created token key file at {}", path.display());
+        Ok(key)
+    }
+}
+
+fn passphrase() -> AppResult<String> {
+    std::env::var(PASSPHRASE_ENV)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| {
+            AppError::Config(format!(
+                "Set {} to use passphrase token encryption",
+                PASSPHRASE_ENV
+            ))
+        })
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = Key::default();
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(dir: &tempfile::TempDir) -> TokenCipher {
+        TokenCipher::new(Some(dir.path().join("token.key")))
+    }
+
+    #[test]
+    fn key_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cipher = cipher(&dir);
+
+        let encrypted = cipher
+            .encrypt("secret-token", TokenEncryption::KeyFile)
+            .unwrap();
+
+        assert!(encrypted.starts_with("enc:v1:key:"));
+        assert!(!encrypted.contains("secret-token"));
+        assert_eq!(encryption_of(&encrypted), TokenEncryption::KeyFile);
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "secret-token");
+        // A fresh nonce every time
+        assert_ne!(
+            cipher
+                .encrypt("secret-token", TokenEncryption::KeyFile)
+                .unwrap(),
+            encrypted
+        );
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let encrypted = cipher(&dir)
+            .encrypt("secret-token", TokenEncryption::KeyFile)
+            .unwrap();
+
+        let other = tempfile::tempdir().unwrap();
+        cipher(&other)
+            .encrypt("other", TokenEncryption::KeyFile)
+            .unwrap();
+
+        assert!(cipher(&other).decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn missing_key_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let encrypted = cipher(&dir)
+            .encrypt("secret-token", TokenEncryption::KeyFile)
+            .unwrap();
+        fs::remove_file(dir.path().join("token.key")).unwrap();
+
+        assert!(cipher(&dir).decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let cipher = cipher(&dir);
+        let encrypted = cipher
+            .encrypt("secret-token", TokenEncryption::KeyFile)
+            .unwrap();
+        let (prefix, encoded) = encrypted.rsplit_once(':').unwrap();
+        let mut payload = BASE64.decode(encoded).unwrap();
+        *payload.last_mut().unwrap() ^= 1;
+
+        let tampered = format!("{}:{}", prefix, BASE64.encode(payload));
+
+        assert!(cipher.decrypt(&tampered).is_err());
+        assert!(cipher.decrypt("enc:v1:key:not base64").is_err());
+        assert!(cipher.decrypt("enc:v1:rot13:AAAA").is_err());
+    }
+
+    // The only test touching the passphrase variable, tests run in parallel
+    #[test]
+    fn passphrase_round_trip_and_wrong_passphrase() {
+        let cipher = TokenCipher::new(None);
+        std::env::set_var(PASSPHRASE_ENV, "correct horse");
+
+        let encrypted = cipher
+            .encrypt("secret-token", TokenEncryption::Passphrase)
+            .unwrap();
+        assert!(encrypted.starts_with("enc:v1:passphrase:"));
+        assert_eq!(encryption_of(&encrypted), TokenEncryption::Passphrase);
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "secret-token");
+
+        std::env::set_var(PASSPHRASE_ENV, "battery staple");
+        assert!(cipher.decrypt(&encrypted).is_err());
+
+        std::env::remove_var(PASSPHRASE_ENV);
+        assert!(cipher.decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn plaintext_passes_through() {
+        let cipher = TokenCipher::new(None);
+
+        assert_eq!(cipher.decrypt("plain-token").unwrap(), "plain-token");
+        assert_eq!(
+            cipher
+                .encrypt("plain-token", TokenEncryption::None)
+                .unwrap(),
+            "plain-token"
+        );
+        assert_eq!(encryption_of("plain-token"), TokenEncryption::None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn key_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        cipher(&dir)
+            .encrypt("secret-token", TokenEncryption::KeyFile)
+            .unwrap();
+
+        let mode = fs::metadata(dir.path().join("token.key"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -4,40 +4,65 @@ mod infrastructure;
 mod presentation;
 
 use crate::application::auth_service::AuthService;
+use crate::application::history_service::{ExportFormat, HistoryService};
 use crate::application::monitor_service::MonitorService;
+use crate::application::server_service::ServerService;
+use crate::domain::models::AppConfig;
 use crate::domain::traits::ConfigStore;
+use crate::infrastructure::artwork_resolver;
 use crate::infrastructure::config_store::FileConfigStore;
 use crate::infrastructure::discord_client::DiscordPresenceClient;
+use crate::infrastructure::history_store::FileHistoryStore;
 use crate::infrastructure::plex_client::ReqwestPlexClient;
-use crate::presentation::ui::PlexDiscordApp;
+use crate::presentation::headless;
+use crate::presentation::ui::{PlexDiscordApp, Services};
 use eframe::egui;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 fn main() -> eframe::Result<()> {
-    env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let is_headless = args.iter().any(|arg| arg == "--headless");
+    let export_path = args
+        .iter()
+        .position(|arg| arg == "--export-history")
+        .map(|idx| args.get(idx + 1).cloned());
+
+    // Headless mode has no other output, so show status changes by default
+    let default_log_level = if is_headless { "info" } else { "error" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_log_level))
+        .init();
 
     // Dependency Injection
-    let config_store = Arc::new(FileConfigStore::new());
-    let config = config_store.load().unwrap_or_default();
+    let history_service = Arc::new(HistoryService::new(Arc::new(FileHistoryStore::new())));
 
-    // Use saved client ID or generate new one
-    let client_id = if config.client_id.is_empty() {
-        Uuid::new_v4().to_string()
-    } else {
-        config.client_id.clone()
-    };
+    if let Some(path) = export_path {
+        std::process::exit(export_history(&history_service, path));
+    }
 
-    let plex_client =
-        Arc::new(ReqwestPlexClient::new(client_id.clone()).expect("Failed to create Plex Client"));
-    let discord_client = Arc::new(Mutex::new(DiscordPresenceClient::new()));
+    let config_store = Arc::new(FileConfigStore::new());
 
-    let auth_service = Arc::new(AuthService::new(plex_client.clone()));
-    let monitor_service = Arc::new(Mutex::new(MonitorService::new(plex_client, discord_client)));
+    if is_headless {
+        let result = config_store.load().and_then(|config| {
+            let services = build_services(&config);
+            headless::run(
+                services.auth_service,
+                services.monitor_service,
+                history_service,
+                config,
+                config_store,
+            )
+        });
+        if let Err(e) = result {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     // Create App
-    let app = PlexDiscordApp::new(auth_service, monitor_service, config_store);
+    let app = PlexDiscordApp::new(build_services, history_service, config_store);
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -53,3 +78,52 @@ fn main() -> eframe::Result<()> {
         Box::new(|_cc| Ok(Box::new(app))),
     )
 }
+
+/// Sets up the clients with the client ID, presence appearance and artwork from `config`.
+fn build_services(config: &AppConfig) -> Services {
+    // Use saved client ID or generate new one
+    let client_id = if config.client_id.is_empty() {
+        Uuid::new_v4().to_string()
+    } else {
+        config.client_id.clone()
+    };
+
+    let plex_client =
+        Arc::new(ReqwestPlexClient::new(client_id).expect("Failed to create Plex Client"));
+    let discord_client = Arc::new(Mutex::new(DiscordPresenceClient::new(
+        config.presence_templates.clone(),
+        config.presence_buttons.clone(),
+    )));
+
+    let artwork_resolver = artwork_resolver::from_config(&config.artwork, plex_client.clone())
+        .expect("Failed to create artwork resolver");
+
+    Services {
+        auth_service: Arc::new(AuthService::new(plex_client.clone())),
+        server_service: Arc::new(ServerService::new(plex_client.clone())),
+        monitor_service: MonitorService::new(plex_client, discord_client, artwork_resolver),
+    }
+}
+
+/// `--export-history <file.csv|file.json>`, returns the exit code.
+fn export_history(history_service: &HistoryService, path: Option<String>) -> i32 {
+    let Some(path) = path.map(std::path::PathBuf::from) else {
+        eprintln!("Usage: plex-discord-rpc --export-history <file.csv|file.json>");
+        return 2;
+    };
+    let Some(format) = ExportFormat::from_path(&path) else {
+        eprintln!("Export file must end in .csv or .json");
+        return 2;
+    };
+
+    match history_service.export(&path, format) {
+        Ok(count) => {
+            println!("Exported {} viewings to {}", count, path.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to export history: {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::application::auth_service::AuthService;
+use crate::application::history_service::HistoryService;
+use crate::application::monitor_service::MonitorService;
+use crate::application::monitor_task::{MonitorHandle, MonitorStatus};
+use crate::domain::models::*;
+use crate::domain::traits::ConfigStore;
+use crate::presentation::{now_playing, status_api};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+
+/// Runs the monitor task without a window until SIGINT/SIGTERM is received.
+///
+/// Requires a `config` that already has a login and a selected server, since
+/// the interactive login and server selection live in the GUI. Changes to it
+/// are saved to `config_store`.
+pub fn run(
+    auth_service: Arc<AuthService>,
+    monitor_service: MonitorService,
+    history_service: Arc<HistoryService>,
+    mut config: AppConfig,
+    config_store: Arc<dyn ConfigStore>,
+) -> AppResult<()> {
+    if !config.is_authenticated() {
+        return Err(AppError::Auth(
+            "No saved Plex login. Sign in once with the desktop app first.".into(),
+        ));
+    }
+
+    if config.servers.is_empty() {
+        return Err(AppError::Config(
+            "No server selected. Pick a server once with the desktop app first.".into(),
+        ));
+    }
+
+    let rt = Runtime::new()?;
+
+    if config.user_id.is_none() {
+        // Configs from older releases only have the username, which is still matched meanwhile
+        match rt.block_on(auth_service.get_account(&config)) {
+            Ok(auth) => {
+                config.user_id = Some(auth.user_id);
+                if let Err(e) = config_store.save(&config) {
+                    log::warn!("Failed to save config: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to look up the Plex account ID: {}", e),
+        }
+    }
+
+    rt.block_on(async move {
+        for server in &config.servers {
+            log::info!("Running headless, monitoring {}", server);
+        }
+
+        let monitor = MonitorHandle::spawn(monitor_service, &tokio::runtime::Handle::current());
+        let mut state = monitor.subscribe_state();
+        let mut events = monitor.subscribe_events();
+        let recorder = history_service.spawn_recorder(
+            monitor.subscribe_events(),
+            &tokio::runtime::Handle::current(),
+        );
+        let now_playing = now_playing::spawn(
+            &config.now_playing,
+            monitor.subscribe_state(),
+            &tokio::runtime::Handle::current(),
+        );
+        if config.status_api.enabled {
+            status_api::spawn(
+                &config.status_api,
+                monitor.subscribe_state(),
+                monitor.subscribe_events(),
+                &tokio::runtime::Handle::current(),
+            )
+            .await?;
+        }
+        monitor.reconfigure(config.clone());
+        if config.incognito != Incognito::Off {
+            log::info!("Incognito is on, Discord won't show anything");
+        }
+
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+
+        let mut fatal_error: Option<AppError> = None;
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                event = events.recv() => match event.map(|e| e.event) {
+                    Ok(event @ (MonitorEvent::DiscordDisconnected { .. }
+                    | MonitorEvent::ServerUnreachable { .. })) => log::warn!("{}", event),
+                    Ok(MonitorEvent::IncognitoEnded) => {
+                        log::info!("Incognito ended");
+                        // Persisted so a restart doesn't turn a finished timer back on
+                        config.incognito = Incognito::Off;
+                        if let Err(e) = config_store.save(&config) {
+                            log::warn!("Failed to save config: {}", e);
+                        }
+                        monitor.reconfigure(config.clone());
+                    }
+                    Ok(event) => log::info!("{}", event),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::debug!("Skipped {} monitor events", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                changed = state.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let status = state.borrow_and_update().status.clone();
+                    if let MonitorStatus::Unauthorized(e) = status {
+                        // Monitoring has stopped, nothing left to do until a new sign in
+                        log::error!("Sign in again with the desktop app.");
+                        fatal_error = Some(AppError::Unauthorized(e));
+                        break;
+                    }
+                }
+            }
+        }
+
+        log::info!("Shutting down");
+        monitor.shutdown().await;
+        // Ends once the monitor's event channel closes, after recording the last viewing
+        let _ = recorder.await;
+        // Empties the files once the monitor's state channel closes
+        if let Some(now_playing) = now_playing {
+            let _ = now_playing.await;
+        }
+
+        match fatal_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Resolves on Ctrl+C, or on SIGTERM where the platform has it.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => log::info!("Received Ctrl+C"),
+        _ = terminate => log::info!("Received SIGTERM"),
+    }
+}
@@ -1 +1,4 @@
+pub mod headless;
+pub mod now_playing;
+pub mod status_api;
 pub mod ui;
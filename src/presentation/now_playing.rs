@@ -0,0 +1,293 @@
+use crate::application::monitor_task::MonitorState;
+use crate::domain::models::*;
+use crate::domain::privacy::{generic_session, FilterAction};
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Keeps the configured now-playing files in sync with the shown session.
+///
+/// Files are rewritten whenever their rendered text changes, emptied while nothing is
+/// shown on Discord, and emptied once more when the monitor shuts down. Returns `None`
+/// when no files are configured.
+pub fn spawn(
+    config: &NowPlayingConfig,
+    mut state: watch::Receiver<MonitorState>,
+    runtime: &tokio::runtime::Handle,
+) -> Option<JoinHandle<()>> {
+    if config.files.is_empty() {
+        return None;
+    }
+    let mut outputs: Vec<Output> = config
+        .files
+        .iter()
+        .map(|file| Output {
+            file: file.clone(),
+            html: is_html(&file.path),
+            written: None,
+            failing: false,
+        })
+        .collect();
+
+    Some(runtime.spawn(async move {
+        loop {
+            let (session, artwork) = {
+                let state = state.borrow_and_update();
+                shown_session(&state)
+            };
+            for output in &mut outputs {
+                let text = match &session {
+                    Some(session) => output.render(session, artwork.as_deref()),
+                    None => String::new(),
+                };
+                output.write(text).await;
+            }
+            if state.changed().await.is_err() {
+                break;
+            }
+        }
+        for output in &mut outputs {
+            output.write(String::new()).await;
+        }
+    }))
+}
+
+/// Empties every configured file, for shutdowns that don't wait for the monitor to stop.
+pub async fn clear(config: &NowPlayingConfig) {
+    for file in &config.files {
+        if let Err(e) = write_atomic(&file.path, "").await {
+            log::warn!("Failed to clear {}: {}", file.path.display(), e);
+        }
+    }
+}
+
+/// The session as Discord shows it after privacy filters and incognito, with its artwork.
+pub fn shown_session(state: &MonitorState) -> (Option<Session>, Option<String>) {
+    match (&state.session, state.presence_filter) {
+        (Some(session), None) => (Some(session.clone()), state.artwork.clone()),
+        (Some(session), Some(FilterAction::Generic)) => (Some(generic_session(session)), None),
+        (Some(_), Some(FilterAction::Hide)) | (None, _) => (None, None),
+    }
+}
+
+struct Output {
+    file: NowPlayingFile,
+    html: bool,
+    /// Text last written, `None` before the first write
+    written: Option<String>,
+    /// Only the first failure in a row is logged as a warning
+    failing: bool,
+}
+
+impl Output {
+    fn render(&self, session: &Session, artwork: Option<&str>) -> String {
+        if self.html {
+            self.file
+                .template
+                .render_with(session, artwork, escape_html)
+        } else {
+            self.file
+                .template
+                .render_with(session, artwork, str::to_string)
+        }
+    }
+
+    async fn write(&mut self, text: String) {
+        if self.written.as_ref() == Some(&text) {
+            return;
+        }
+        match write_atomic(&self.file.path, &text).await {
+            Ok(()) => {
+                self.written = Some(text);
+                self.failing = false;
+            }
+            Err(e) if self.failing => {
+                log::debug!("Failed to write {}: {}", self.file.path.display(), e);
+            }
+            Err(e) => {
+                log::warn!("Failed to write {}: {}", self.file.path.display(), e);
+                self.failing = true;
+            }
+        }
+    }
+}
+
+/// Writes through a temporary file next to `path`, so readers polling the file
+/// never see it half-written.
+async fn write_atomic(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path: PathBuf = path.with_file_name(tmp_name);
+    tokio::fs::write(&tmp_path, text).await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::monitor_task::MonitorStatus;
+    use crate::domain::template::PresenceTemplate;
+    use std::time::{Duration, Instant};
+
+    fn state(session: Option<Session>, presence_filter: Option<FilterAction>) -> MonitorState {
+        MonitorState {
+            status: MonitorStatus::Monitoring,
+            session,
+            presence_filter,
+            artwork: Some("https://example.com/heat.jpg".to_string()),
+            incognito: false,
+            discord_connected: true,
+            unreachable_servers: Vec::new(),
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn heat() -> Option<Session> {
+        Some(Session::for_test(MediaType::Movie, "Heat"))
+    }
+
+    fn file(path: PathBuf, template: &str) -> NowPlayingFile {
+        NowPlayingFile {
+            path,
+            template: PresenceTemplate::parse(template).unwrap(),
+        }
+    }
+
+    /// Waits for `path` to hold `expected`, the writer task runs on its own.
+    async fn wait_for_contents(path: &Path, expected: &str) {
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            while std::fs::read_to_string(path).ok().as_deref() != Some(expected) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await;
+        assert!(
+            result.is_ok(),
+            "{} holds {:?}, expected {:?}",
+            path.display(),
+            std::fs::read_to_string(path).ok(),
+            expected
+        );
+    }
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(
+            escape_html(r#"<b>Tom & Jerry's "Show"</b>"#),
+            "&lt;b&gt;Tom &amp; Jerry&#39;s &quot;Show&quot;&lt;/b&gt;"
+        );
+        assert_eq!(escape_html("Amélie"), "Amélie");
+    }
+
+    #[test]
+    fn html_files_by_extension() {
+        assert!(is_html(Path::new("overlay/now-playing.html")));
+        assert!(is_html(Path::new("NOW.HTM")));
+        assert!(!is_html(Path::new("now-playing.txt")));
+        assert!(!is_html(Path::new("html")));
+    }
+
+    #[test]
+    fn shows_sessions_like_discord() {
+        let (session, artwork) = shown_session(&state(heat(), None));
+        assert_eq!(session.map(|s| s.media_title).as_deref(), Some("Heat"));
+        assert_eq!(artwork.as_deref(), Some("https://example.com/heat.jpg"));
+
+        let (session, artwork) = shown_session(&state(heat(), Some(FilterAction::Generic)));
+        assert_eq!(
+            session.map(|s| s.media_title).as_deref(),
+            Some("Watching something")
+        );
+        assert_eq!(artwork, None);
+
+        assert!(matches!(
+            shown_session(&state(heat(), Some(FilterAction::Hide))),
+            (None, None)
+        ));
+        assert!(matches!(shown_session(&state(None, None)), (None, None)));
+    }
+
+    #[tokio::test]
+    async fn empties_files_when_nothing_plays_and_on_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("now-playing.txt");
+        let html = dir.path().join("now-playing.html");
+        let config = NowPlayingConfig {
+            files: vec![
+                file(text.clone(), "{title}"),
+                file(html.clone(), "<p>{title}</p>"),
+            ],
+        };
+        let mut playing = state(heat(), None);
+        playing.session.as_mut().unwrap().media_title = "Tom & Jerry".to_string();
+        let (tx, rx) = watch::channel(playing);
+
+        let task = spawn(&config, rx, &tokio::runtime::Handle::current()).unwrap();
+        wait_for_contents(&text, "Tom & Jerry").await;
+        wait_for_contents(&html, "<p>Tom &amp; Jerry</p>").await;
+
+        tx.send_replace(state(None, None));
+        wait_for_contents(&text, "").await;
+
+        tx.send_replace(state(heat(), None));
+        wait_for_contents(&text, "Heat").await;
+
+        // The monitor shutting down closes the channel
+        drop(tx);
+        task.await.unwrap();
+        assert_eq!(std::fs::read_to_string(&text).unwrap(), "");
+        assert_eq!(std::fs::read_to_string(&html).unwrap(), "");
+    }
+
+    #[test]
+    fn no_task_without_files() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (_tx, rx) = watch::channel(state(heat(), None));
+        assert!(spawn(&NowPlayingConfig::default(), rx, runtime.handle()).is_none());
+    }
+
+    #[tokio::test]
+    async fn unchanged_text_is_not_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("now-playing.txt");
+        let mut output = Output {
+            file: file(path.clone(), "{title}"),
+            html: false,
+            written: None,
+            failing: false,
+        };
+
+        output.write("Heat".to_string()).await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Heat");
+
+        // Same text again: the file isn't touched, so it stays gone
+        std::fs::remove_file(&path).unwrap();
+        output.write("Heat".to_string()).await;
+        assert!(!path.exists());
+
+        output.write("Ronin".to_string()).await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Ronin");
+        assert!(!dir.path().join("now-playing.txt.tmp").exists());
+    }
+}
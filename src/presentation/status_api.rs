@@ -0,0 +1,530 @@
+use crate::application::monitor_task::{MonitorState, MonitorStatus};
+use crate::domain::models::*;
+use crate::domain::privacy::FilterAction;
+use crate::presentation::now_playing::shown_session;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http_body_util::{combinators::BoxBody, BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{HeaderValue, ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+/// Events kept for `GET /events`.
+const RECENT_EVENTS: usize = 50;
+/// SSE comment sent when nothing happened for this long, keeps proxies from timing out.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Snapshot of the monitor as served by `GET /status`.
+#[derive(Serialize)]
+struct StatusView<'a> {
+    status: &'static str,
+    /// Why monitoring stopped, for `unauthorized`
+    error: Option<&'a str>,
+    player_state: Option<PlayerState>,
+    /// The session as Discord shows it, unless `StatusApiConfig::include_hidden` is set
+    session: Option<Session>,
+    /// Artwork URL or asset key shown on Discord
+    artwork: Option<String>,
+    discord_connected: bool,
+    incognito: bool,
+    /// How the privacy filters or incognito changed what Discord shows
+    presence_filter: Option<FilterAction>,
+    unreachable_servers: &'a [String],
+}
+
+impl<'a> StatusView<'a> {
+    fn new(state: &'a MonitorState, include_hidden: bool) -> Self {
+        let (status, error) = match &state.status {
+            MonitorStatus::Stopped => ("stopped", None),
+            MonitorStatus::Monitoring => ("monitoring", None),
+            MonitorStatus::Unauthorized(e) => ("unauthorized", Some(e.as_str())),
+        };
+        let (session, artwork) = if include_hidden {
+            (state.session.clone(), state.artwork.clone())
+        } else {
+            shown_session(state)
+        };
+        Self {
+            status,
+            error,
+            player_state: session.as_ref().map(|s| s.player_state.clone()),
+            session,
+            artwork,
+            discord_connected: state.discord_connected,
+            incognito: state.incognito,
+            presence_filter: state.presence_filter,
+            unreachable_servers: &state.unreachable_servers,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct RecordedEvent {
+    /// Unix time in seconds
+    at: u64,
+    #[serde(flatten)]
+    event: MonitorEvent,
+}
+
+struct StatusApi {
+    state: watch::Receiver<MonitorState>,
+    /// Template for per-connection subscriptions, never read itself
+    events: broadcast::Receiver<FilteredEvent>,
+    recent: Mutex<VecDeque<RecordedEvent>>,
+    allowed_origin: Option<HeaderValue>,
+    include_hidden: bool,
+}
+
+/// Serves the monitor state over HTTP until the runtime shuts down.
+///
+/// - `GET /status`: the current state as JSON
+/// - `GET /events`: the most recent monitor events, oldest first
+/// - `GET /events/stream`: Server-Sent Events, the status on connect, then every event
+///   as it happens and the status whenever it changes
+pub async fn spawn(
+    config: &StatusApiConfig,
+    state: watch::Receiver<MonitorState>,
+    events: broadcast::Receiver<FilteredEvent>,
+    runtime: &tokio::runtime::Handle,
+) -> AppResult<JoinHandle<()>> {
+    let listener = TcpListener::bind((config.bind.as_str(), config.port)).await?;
+    serve(listener, config, state, events, runtime)
+}
+
+fn serve(
+    listener: TcpListener,
+    config: &StatusApiConfig,
+    state: watch::Receiver<MonitorState>,
+    events: broadcast::Receiver<FilteredEvent>,
+    runtime: &tokio::runtime::Handle,
+) -> AppResult<JoinHandle<()>> {
+    let address = listener.local_addr()?;
+    if address.ip().is_loopback() {
+        log::info!("Status API listening on http://{}", address);
+    } else {
+        log::warn!(
+            "Status API listening on http://{}, reachable from other machines",
+            address
+        );
+    }
+
+    let allowed_origin = match &config.allowed_origin {
+        Some(origin) => Some(
+            HeaderValue::from_str(origin)
+                .map_err(|_| AppError::Config(format!("Invalid status API origin '{}'", origin)))?,
+        ),
+        None => None,
+    };
+    let api = Arc::new(StatusApi {
+        state,
+        events: events.resubscribe(),
+        recent: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)),
+        allowed_origin,
+        include_hidden: config.include_hidden,
+    });
+
+    runtime.spawn(record_events(api.clone(), events));
+    Ok(runtime.spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::warn!("Status API failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            let api = api.clone();
+            tokio::spawn(async move {
+                let service = hyper::service::service_fn(move |request| {
+                    let api = api.clone();
+                    async move { Ok::<_, Infallible>(api.handle(request)) }
+                });
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service)
+                    .await
+                {
+                    log::debug!("Status API connection closed: {}", e);
+                }
+            });
+        }
+    }))
+}
+
+async fn record_events(api: Arc<StatusApi>, mut events: broadcast::Receiver<FilteredEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => match visible(&event, api.include_hidden) {
+                Some(event) => event,
+                None => continue,
+            },
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let mut recent = api.recent.lock().unwrap();
+        if recent.len() == RECENT_EVENTS {
+            recent.pop_front();
+        }
+        recent.push_back(RecordedEvent {
+            at: unix_now(),
+            event,
+        });
+    }
+}
+
+impl StatusApi {
+    fn handle(&self, request: Request<Incoming>) -> Response<Body> {
+        if request.method() != Method::GET {
+            return self.error(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported");
+        }
+        let mut response = match request.uri().path().trim_end_matches('/') {
+            "/status" => {
+                let state = self.state.borrow().clone();
+                json_response(&StatusView::new(&state, self.include_hidden))
+            }
+            "/events" => {
+                let recent: Vec<RecordedEvent> =
+                    self.recent.lock().unwrap().iter().cloned().collect();
+                json_response(&recent)
+            }
+            "/events/stream" => self.event_stream(),
+            _ => return self.error(StatusCode::NOT_FOUND, "Unknown endpoint"),
+        };
+        self.add_cors(&mut response);
+        response
+    }
+
+    fn event_stream(&self) -> Response<Body> {
+        let mut state = self.state.clone();
+        let initial = status_json(&state.borrow_and_update(), self.include_hidden);
+        let stream = EventStream {
+            pending: Some(sse_message("status", &initial)),
+            last_status: initial,
+            state,
+            include_hidden: self.include_hidden,
+            events: self.events.resubscribe(),
+        };
+
+        let stream = futures_util::stream::unfold(stream, |mut stream| async move {
+            let message = stream.next_message().await?;
+            Some((message, stream))
+        });
+
+        let body = StreamBody::new(stream.map(|data| Ok(Frame::data(data))));
+        let mut response = Response::new(BodyExt::boxed(body));
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        response
+    }
+
+    fn error(&self, status: StatusCode, message: &str) -> Response<Body> {
+        let mut response = json_response(&serde_json::json!({ "error": message }));
+        *response.status_mut() = status;
+        self.add_cors(&mut response);
+        response
+    }
+
+    fn add_cors(&self, response: &mut Response<Body>) {
+        if let Some(origin) = &self.allowed_origin {
+            response
+                .headers_mut()
+                .insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        }
+    }
+}
+
+fn json_response<T: Serialize>(value: &T) -> Response<Body> {
+    // Every type served here serializes infallibly
+    let json = serde_json::to_vec(value).unwrap_or_default();
+    let mut response = Response::new(Full::new(Bytes::from(json)).boxed());
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// One SSE connection: the status on connect, then every event and every status change.
+struct EventStream {
+    pending: Option<Bytes>,
+    /// Last status sent, the monitor republishes its state even when nothing shown changed
+    last_status: String,
+    state: watch::Receiver<MonitorState>,
+    include_hidden: bool,
+    events: broadcast::Receiver<FilteredEvent>,
+}
+
+impl EventStream {
+    /// `None` once the monitor has shut down.
+    async fn next_message(&mut self) -> Option<Bytes> {
+        if let Some(message) = self.pending.take() {
+            return Some(message);
+        }
+        loop {
+            let sleep = tokio::time::sleep(KEEP_ALIVE);
+            tokio::select! {
+                event = self.events.recv() => match event {
+                    Ok(event) => {
+                        let Some(event) = visible(&event, self.include_hidden) else {
+                            continue;
+                        };
+                        let recorded = RecordedEvent {
+                            at: unix_now(),
+                            event,
+                        };
+                        let name = event_name(&recorded.event);
+                        return Some(sse_message(&name, &serde_json::to_string(&recorded).unwrap_or_default()));
+                    }
+                    // Missed events are summed up by the status sent on the next change
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+                changed = self.state.changed() => {
+                    changed.ok()?;
+                    let status = status_json(&self.state.borrow_and_update(), self.include_hidden);
+                    if status != self.last_status {
+                        self.last_status = status;
+                        return Some(sse_message("status", &self.last_status));
+                    }
+                }
+                _ = sleep => return Some(Bytes::from_static(b": keep-alive\n\n")),
+            }
+        }
+    }
+}
+
+/// The event as served: as Discord showed it unless `include_hidden` is set.
+fn visible(event: &FilteredEvent, include_hidden: bool) -> Option<MonitorEvent> {
+    if include_hidden {
+        Some(event.event.clone())
+    } else {
+        event.shown()
+    }
+}
+
+fn status_json(state: &MonitorState, include_hidden: bool) -> String {
+    serde_json::to_string(&StatusView::new(state, include_hidden)).unwrap_or_default()
+}
+
+fn sse_message(event: &str, json: &str) -> Bytes {
+    Bytes::from(format!("event: {}\ndata: {}\n\n", event, json))
+}
+
+/// The `event` tag `MonitorEvent` serializes with, e.g. `session_started`.
+fn event_name(event: &MonitorEvent) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|value| value.get("event")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| "event".to_string())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn state(presence_filter: Option<FilterAction>) -> MonitorState {
+        MonitorState {
+            status: MonitorStatus::Monitoring,
+            session: Some(Session::for_test(MediaType::Movie, "Heat")),
+            presence_filter,
+            artwork: Some("https://example.com/heat.jpg".to_string()),
+            incognito: false,
+            discord_connected: true,
+            unreachable_servers: Vec::new(),
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn shown_title(view: &StatusView) -> Option<String> {
+        view.session.as_ref().map(|s| s.media_title.clone())
+    }
+
+    #[test]
+    fn serves_the_session_as_discord_shows_it() {
+        let shown = state(None);
+        let view = StatusView::new(&shown, false);
+        assert_eq!(shown_title(&view).as_deref(), Some("Heat"));
+        assert_eq!(
+            view.artwork.as_deref(),
+            Some("https://example.com/heat.jpg")
+        );
+
+        let generic = state(Some(FilterAction::Generic));
+        let view = StatusView::new(&generic, false);
+        assert_eq!(shown_title(&view).as_deref(), Some("Watching something"));
+        assert_eq!(view.player_state, Some(PlayerState::Playing));
+        assert_eq!(view.artwork, None);
+
+        let hidden = state(Some(FilterAction::Hide));
+        let view = StatusView::new(&hidden, false);
+        assert_eq!(shown_title(&view), None);
+        assert_eq!(view.player_state, None);
+        assert_eq!(view.presence_filter, Some(FilterAction::Hide));
+    }
+
+    #[test]
+    fn include_hidden_serves_the_real_session() {
+        let hidden = state(Some(FilterAction::Hide));
+        let view = StatusView::new(&hidden, true);
+        assert_eq!(shown_title(&view).as_deref(), Some("Heat"));
+        assert_eq!(view.player_state, Some(PlayerState::Playing));
+    }
+
+    struct Api {
+        address: SocketAddr,
+        events: broadcast::Sender<FilteredEvent>,
+        // Kept so the API doesn't see the monitor shut down
+        _state: watch::Sender<MonitorState>,
+    }
+
+    /// Serves a monitor state whose "Secret" session is hidden from Discord.
+    async fn start(include_hidden: bool) -> Api {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut hidden = state(Some(FilterAction::Hide));
+        hidden.session = Some(secret());
+        let (state_tx, state) = watch::channel(hidden);
+        let (events, events_rx) = broadcast::channel(16);
+        let config = StatusApiConfig {
+            include_hidden,
+            ..StatusApiConfig::default()
+        };
+        serve(
+            listener,
+            &config,
+            state,
+            events_rx,
+            &tokio::runtime::Handle::current(),
+        )
+        .unwrap();
+        Api {
+            address,
+            events,
+            _state: state_tx,
+        }
+    }
+
+    fn secret() -> Session {
+        Session::for_test(MediaType::Movie, "Secret")
+    }
+
+    /// Hidden and generic events for the "Secret" session, then a shown "Heat" one.
+    fn publish_events(api: &Api) {
+        let hide = Some(FilterAction::Hide);
+        for event in [
+            MonitorEvent::SessionStarted(secret()).filtered(hide),
+            MonitorEvent::Paused(secret()).filtered(hide),
+            MonitorEvent::ServerUnreachable {
+                server: "Home".to_string(),
+                error: "timed out".to_string(),
+                session: Some(secret()),
+            }
+            .filtered(hide),
+            MonitorEvent::Ended(secret()).filtered(hide),
+            MonitorEvent::SessionStarted(secret()).filtered(Some(FilterAction::Generic)),
+            MonitorEvent::SessionStarted(Session::for_test(MediaType::Movie, "Heat"))
+                .filtered(None),
+        ] {
+            api.events.send(event).unwrap();
+        }
+    }
+
+    async fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// `/events` once the recorder has caught up with the "Heat" event.
+    async fn recorded_events(address: SocketAddr) -> String {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let response = get(address, "/events").await;
+                if response.contains("Heat") {
+                    return response;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn hidden_sessions_stay_off_every_route() {
+        let api = start(false).await;
+
+        let mut stream = TcpStream::connect(api.address).await.unwrap();
+        stream
+            .write_all(b"GET /events/stream HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut streamed = String::new();
+        let mut buffer = [0; 4096];
+        // Published only once the stream has subscribed, with its status on connect
+        while !streamed.contains("event: status") {
+            let read = stream.read(&mut buffer).await.unwrap();
+            streamed.push_str(&String::from_utf8_lossy(&buffer[..read]));
+        }
+        publish_events(&api);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !streamed.contains("Heat") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                streamed.push_str(&String::from_utf8_lossy(&buffer[..read]));
+            }
+        })
+        .await
+        .unwrap();
+
+        let status = get(api.address, "/status").await;
+        let recorded = recorded_events(api.address).await;
+
+        for response in [&status, &recorded, &streamed] {
+            assert!(!response.contains("Secret"), "{}", response);
+        }
+        assert!(status.contains(r#""presence_filter":"hide""#));
+        assert!(recorded.contains("Watching something"));
+        assert!(recorded.contains("server_unreachable"));
+        assert!(streamed.contains("Watching something"));
+        assert!(streamed.contains("event: server_unreachable"));
+    }
+
+    #[tokio::test]
+    async fn include_hidden_serves_hidden_sessions_on_every_route() {
+        let api = start(true).await;
+        publish_events(&api);
+
+        let status = get(api.address, "/status").await;
+        let recorded = recorded_events(api.address).await;
+
+        assert!(status.contains("Secret"));
+        assert!(recorded.contains("Secret"));
+        assert!(!recorded.contains("Watching something"));
+    }
+}
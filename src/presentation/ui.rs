@@ -1,12 +1,20 @@
 use crate::application::auth_service::AuthService;
+use crate::application::history_service::{ExportFormat, HistoryService};
 use crate::application::monitor_service::MonitorService;
+use crate::application::monitor_task::{MonitorHandle, MonitorState, MonitorStatus};
+use crate::application::server_service::ServerService;
+use crate::application::statistics::{PeriodTotal, TitleTotal, WatchStatistics};
 use crate::domain::models::*;
+use crate::domain::privacy::{generic_session, FilterAction};
 use crate::domain::traits::ConfigStore;
+use crate::presentation::{now_playing, status_api};
 use eframe::egui;
 use std::collections::VecDeque;
 use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
 
 // --- Enums for Async Communication ---
 pub enum AppMessage {
@@ -14,15 +22,19 @@ pub enum AppMessage {
     AuthUrlReady(OAuthPinInfo),
     AuthCheckResult(Option<PlexAuth>),
     AuthFailed(String),
+    AccountFetched(PlexAuth),
     ServersFetched(Vec<PlexServer>),
     ServersFetchFailed(String),
     VerificationStarted,
-    VerificationSuccess(String),
+    VerificationSuccess(PlexServer),
     VerificationFailed(String),
-    MonitorUpdate(String),
-    MonitorError(String),
+    SessionExpired(String),
     ConfigSaved,
     ConfigSaveFailed(String),
+    HistoryExported(usize, std::path::PathBuf),
+    HistoryExportFailed(String),
+    StatisticsLoaded(WatchStatistics),
+    StatisticsFailed(String),
 }
 
 // --- Notification System ---
@@ -44,24 +56,61 @@ enum NotificationKind {
 // --- Activity Details ---
 #[derive(Clone, Default)]
 struct ActivityInfo {
-    status: String,
+    session: Option<Session>,
+    presence_filter: Option<FilterAction>,
+    incognito: bool,
     last_update: Option<Instant>,
-    is_playing: bool,
-    is_paused: bool,
+    discord_connected: bool,
+    unreachable_servers: Vec<String>,
+}
+
+// --- Dashboard Tabs ---
+#[derive(Clone, Copy, PartialEq)]
+enum DashboardTab {
+    Overview,
+    Statistics,
+}
+
+// --- Dashboard Server List ---
+enum ServerListAction {
+    MoveUp(usize),
+    MoveDown(usize),
+    Remove(usize),
+}
+
+/// Clients and services set up from the loaded config.
+pub struct Services {
+    pub auth_service: Arc<AuthService>,
+    pub server_service: Arc<ServerService>,
+    pub monitor_service: MonitorService,
 }
 
 // --- Main App Struct ---
 pub struct PlexDiscordApp {
     // Services
+    /// Sets up the services again once a broken config loads after all
+    build_services: fn(&AppConfig) -> Services,
     auth_service: Arc<AuthService>,
-    monitor_service: Arc<tokio::sync::Mutex<MonitorService>>,
+    server_service: Arc<ServerService>,
+    history_service: Arc<HistoryService>,
+    monitor: MonitorHandle,
+    monitor_state: watch::Receiver<MonitorState>,
+    monitor_events: broadcast::Receiver<FilteredEvent>,
+    /// Config the monitor task currently runs with, `None` while stopped
+    monitor_config: Option<AppConfig>,
     config_store: Arc<dyn ConfigStore>,
+    /// Task writing the now-playing files, `None` when none are configured
+    now_playing: Option<JoinHandle<()>>,
 
     // State
     config: AppConfig,
     app_state: ApplicationState,
     oauth_info: Option<OAuthPinInfo>,
+    /// Why `config.json` couldn't be loaded, shown instead of the normal screens
+    config_error: Option<String>,
     servers: Vec<PlexServer>,
+    /// Server being verified before it is added to `config.servers`
+    pending_server: Option<PlexServer>,
 
     // UI State & feedback
     is_loading_servers: bool,
@@ -69,6 +118,15 @@ pub struct PlexDiscordApp {
     is_verifying: bool,
     notifications: VecDeque<Notification>,
     activity_info: ActivityInfo,
+    dashboard_tab: DashboardTab,
+    statistics: Option<WatchStatistics>,
+    is_loading_statistics: bool,
+
+    // Credentials Login Input
+    login_username: String,
+    login_password: String,
+    login_verification_code: String,
+    is_signing_in: bool,
 
     // Manual Connection Input
     custom_server_ip: String,
@@ -82,63 +140,136 @@ pub struct PlexDiscordApp {
 
     // Tickers
     last_oauth_poll: Instant,
-    last_monitor_tick: Instant,
 }
 
 impl PlexDiscordApp {
     pub fn new(
-        auth_service: Arc<AuthService>,
-        monitor_service: Arc<tokio::sync::Mutex<MonitorService>>,
+        build_services: fn(&AppConfig) -> Services,
+        history_service: Arc<HistoryService>,
         config_store: Arc<dyn ConfigStore>,
     ) -> Self {
         let (tx, rx) = mpsc::channel();
-        let config = config_store.load().unwrap_or_default();
-
-        // Determine initial state based on config
-        let app_state = if config.is_authenticated() {
-            if config.server_address.is_some() {
-                ApplicationState::Verifying // Start with verification
-            } else {
-                ApplicationState::ServerSelection
-            }
+        let rt = Runtime::new().unwrap();
+        // A broken config is never replaced silently, the user decides on the error screen
+        let (config, config_error) = match config_store.load() {
+            Ok(config) => (config, None),
+            Err(e) => (AppConfig::default(), Some(e.to_string())),
+        };
+        // Stand-ins until the config loads, nothing is monitored on the error screen
+        let services = build_services(&config);
+        let monitor = MonitorHandle::spawn(services.monitor_service, rt.handle());
+        history_service.spawn_recorder(monitor.subscribe_events(), rt.handle());
+        let monitor_state = monitor.subscribe_state();
+        let monitor_events = monitor.subscribe_events();
+        let app_state = if config_error.is_some() {
+            ApplicationState::ConfigError
         } else {
-            ApplicationState::Login
+            Self::initial_state(&config)
         };
 
         let mut app = Self {
-            auth_service,
-            monitor_service,
+            build_services,
+            auth_service: services.auth_service,
+            server_service: services.server_service,
+            history_service,
+            monitor,
+            monitor_state,
+            monitor_events,
+            monitor_config: None,
             config_store,
+            now_playing: None,
             config,
             app_state,
             oauth_info: None,
+            config_error,
             servers: Vec::new(),
+            pending_server: None,
             is_loading_servers: false,
             is_checking_auth: false,
             is_verifying: false,
             notifications: VecDeque::new(),
             activity_info: ActivityInfo::default(),
+            dashboard_tab: DashboardTab::Overview,
+            statistics: None,
+            is_loading_statistics: false,
+            login_username: String::new(),
+            login_password: String::new(),
+            login_verification_code: String::new(),
+            is_signing_in: false,
             custom_server_ip: String::new(),
             custom_server_port: "32400".to_string(),
             custom_server_owned: true,
-            rt: Runtime::new().unwrap(),
+            rt,
             tx,
             rx,
             last_oauth_poll: Instant::now(),
-            last_monitor_tick: Instant::now(),
         };
 
+        if app.config_error.is_none() {
+            app.spawn_outputs();
+        }
+
+        if app.config.is_authenticated() && app.config.user_id.is_none() {
+            app.dispatch_fetch_account();
+        }
+
         // If we start in ServerSelection, fetch servers immediately
         if app.app_state == ApplicationState::ServerSelection {
             app.dispatch_fetch_servers();
         }
 
-        // If we start in Verifying, verify the connection
-        if app.app_state == ApplicationState::Verifying {
-            app.dispatch_verify_connection();
+        app
+    }
+
+    /// Starts the now-playing writer and the status API configured in `config`.
+    fn spawn_outputs(&mut self) {
+        self.now_playing = now_playing::spawn(
+            &self.config.now_playing,
+            self.monitor.subscribe_state(),
+            self.rt.handle(),
+        );
+        if self.config.status_api.enabled {
+            let api = status_api::spawn(
+                &self.config.status_api,
+                self.monitor.subscribe_state(),
+                self.monitor.subscribe_events(),
+                self.rt.handle(),
+            );
+            if let Err(e) = self.rt.block_on(api) {
+                self.add_notification(
+                    format!("Status API failed to start: {}", e),
+                    NotificationKind::Error,
+                );
+            }
         }
+    }
 
-        app
+    /// Replaces the stand-in services with ones set up from the loaded config.
+    /// The old monitor task stops when its handle is dropped.
+    fn rebuild_services(&mut self) {
+        let services = (self.build_services)(&self.config);
+        self.auth_service = services.auth_service;
+        self.server_service = services.server_service;
+        self.monitor = MonitorHandle::spawn(services.monitor_service, self.rt.handle());
+        self.history_service
+            .spawn_recorder(self.monitor.subscribe_events(), self.rt.handle());
+        self.monitor_state = self.monitor.subscribe_state();
+        self.monitor_events = self.monitor.subscribe_events();
+        self.monitor_config = None;
+        self.spawn_outputs();
+    }
+
+    fn initial_state(config: &AppConfig) -> ApplicationState {
+        if config.is_authenticated() {
+            if !config.servers.is_empty() {
+                // Saved servers were verified when added, failures show on the dashboard
+                ApplicationState::Running
+            } else {
+                ApplicationState::ServerSelection
+            }
+        } else {
+            ApplicationState::Login
+        }
     }
 
     // --- Action Dispatchers (Spawn Async Tasks) ---
@@ -158,6 +289,24 @@ impl PlexDiscordApp {
         });
     }
 
+    /// Looks up the account ID for configs saved before it was stored.
+    fn dispatch_fetch_account(&self) {
+        let tx = self.tx.clone();
+        let service = self.auth_service.clone();
+        let config = self.config.clone();
+
+        self.rt.spawn(async move {
+            match service.get_account(&config).await {
+                Ok(auth) => tx.send(AppMessage::AccountFetched(auth)).ok(),
+                // Sessions are matched by username meanwhile, retried on the next start
+                Err(e) => {
+                    log::warn!("Failed to look up the Plex account ID: {}", e);
+                    None
+                }
+            };
+        });
+    }
+
     fn dispatch_start_login(&self) {
         let tx = self.tx.clone();
         let service = self.auth_service.clone();
@@ -172,6 +321,32 @@ impl PlexDiscordApp {
         });
     }
 
+    fn dispatch_credentials_login(&mut self) {
+        if self.is_signing_in {
+            return;
+        }
+
+        self.is_signing_in = true;
+        let tx = self.tx.clone();
+        let service = self.auth_service.clone();
+        let login = self.login_username.clone();
+        // Don't keep the password around once it's sent
+        let password = std::mem::take(&mut self.login_password);
+        let code = std::mem::take(&mut self.login_verification_code);
+
+        tx.send(AppMessage::AuthStarted).ok();
+
+        self.rt.spawn(async move {
+            match service
+                .login_with_credentials(&login, &password, Some(code))
+                .await
+            {
+                Ok(auth) => tx.send(AppMessage::AuthCheckResult(Some(auth))).ok(),
+                Err(e) => tx.send(AppMessage::AuthFailed(e.to_string())).ok(),
+            };
+        });
+    }
+
     fn dispatch_check_auth(&mut self) {
         if self.last_oauth_poll.elapsed() < Duration::from_secs(2) || self.is_checking_auth {
             return;
@@ -202,13 +377,13 @@ impl PlexDiscordApp {
 
         self.is_loading_servers = true;
         let tx = self.tx.clone();
-        let monitor = self.monitor_service.clone();
+        let service = self.server_service.clone();
         let config = self.config.clone();
 
         self.rt.spawn(async move {
-            let service = monitor.lock().await;
             match service.get_servers(&config).await {
                 Ok(servers) => tx.send(AppMessage::ServersFetched(servers)).ok(),
+                Err(AppError::Unauthorized(e)) => tx.send(AppMessage::SessionExpired(e)).ok(),
                 Err(e) => tx
                     .send(AppMessage::ServersFetchFailed(format!("{:?}", e)))
                     .ok(),
@@ -216,22 +391,22 @@ impl PlexDiscordApp {
         });
     }
 
-    fn dispatch_verify_connection(&mut self) {
+    fn dispatch_verify_connection(&mut self, server: PlexServer) {
         if self.is_verifying {
             return;
         }
 
         self.is_verifying = true;
         let tx = self.tx.clone();
-        let monitor = self.monitor_service.clone();
+        let service = self.server_service.clone();
         let config = self.config.clone();
 
         tx.send(AppMessage::VerificationStarted).ok();
 
         self.rt.spawn(async move {
-            let mut service = monitor.lock().await;
-            match service.update(&config).await {
-                Ok(msg) => tx.send(AppMessage::VerificationSuccess(msg)).ok(),
+            match service.verify_server(&server, &config).await {
+                Ok(server) => tx.send(AppMessage::VerificationSuccess(server)).ok(),
+                Err(AppError::Unauthorized(e)) => tx.send(AppMessage::SessionExpired(e)).ok(),
                 Err(e) => tx
                     .send(AppMessage::VerificationFailed(format!("{:?}", e)))
                     .ok(),
@@ -239,23 +414,24 @@ impl PlexDiscordApp {
         });
     }
 
-    fn dispatch_monitor_tick(&mut self) {
-        if self.last_monitor_tick.elapsed() < Duration::from_secs(3) {
+    /// Starts, reconfigures or stops the monitor task whenever the config it needs changes.
+    fn sync_monitor(&mut self) {
+        let wanted = (self.app_state != ApplicationState::ConfigError
+            && self.config.is_authenticated()
+            && !self.config.servers.is_empty())
+        .then(|| self.config.clone());
+
+        if wanted == self.monitor_config {
             return;
         }
-        self.last_monitor_tick = Instant::now();
-
-        let tx = self.tx.clone();
-        let monitor = self.monitor_service.clone();
-        let config = self.config.clone();
-
-        self.rt.spawn(async move {
-            let mut service = monitor.lock().await;
-            match service.update(&config).await {
-                Ok(msg) => tx.send(AppMessage::MonitorUpdate(msg)).ok(),
-                Err(e) => tx.send(AppMessage::MonitorError(format!("{:?}", e))).ok(),
-            };
-        });
+        match &wanted {
+            Some(config) => self.monitor.reconfigure(config.clone()),
+            None => {
+                self.monitor.disconnect();
+                self.activity_info = ActivityInfo::default();
+            }
+        }
+        self.monitor_config = wanted;
     }
 
     // --- Helper Methods ---
@@ -292,8 +468,10 @@ impl PlexDiscordApp {
                 }
                 AppMessage::AuthCheckResult(Some(auth)) => {
                     self.is_checking_auth = false;
+                    self.is_signing_in = false;
                     self.config.auth_token = Some(auth.auth_token);
                     self.config.username = Some(auth.username);
+                    self.config.user_id = Some(auth.user_id);
                     self.dispatch_save_config();
                     self.app_state = ApplicationState::ServerSelection;
                     self.oauth_info = None;
@@ -303,11 +481,19 @@ impl PlexDiscordApp {
                     );
                     self.dispatch_fetch_servers();
                 }
+                AppMessage::AccountFetched(auth) => {
+                    // Ignore answers for a login that has since changed
+                    if self.config.auth_token.as_ref() == Some(&auth.auth_token) {
+                        self.config.user_id = Some(auth.user_id);
+                        self.dispatch_save_config();
+                    }
+                }
                 AppMessage::AuthCheckResult(None) => {
                     self.is_checking_auth = false;
                 }
                 AppMessage::AuthFailed(e) => {
                     self.is_checking_auth = false;
+                    self.is_signing_in = false;
                     self.add_notification(e, NotificationKind::Error);
                 }
                 AppMessage::ServersFetched(servers) => {
@@ -338,38 +524,75 @@ impl PlexDiscordApp {
                         NotificationKind::Info,
                     );
                 }
-                AppMessage::VerificationSuccess(msg) => {
+                AppMessage::VerificationSuccess(server) => {
                     self.is_verifying = false;
+                    self.pending_server = None;
+                    let message = format!("Connected to {} successfully!", server.name);
+                    // Reconnecting to a known address replaces the old entry in place
+                    match self
+                        .config
+                        .servers
+                        .iter_mut()
+                        .find(|s| s.is_same_server(&server))
+                    {
+                        Some(existing) => *existing = server,
+                        None => self.config.servers.push(server),
+                    }
                     self.dispatch_save_config();
                     self.app_state = ApplicationState::Running;
-                    self.activity_info.status = msg;
-                    self.activity_info.last_update = Some(Instant::now());
-                    self.add_notification(
-                        "Connected to server successfully!".to_string(),
-                        NotificationKind::Success,
-                    );
+                    self.add_notification(message, NotificationKind::Success);
                 }
                 AppMessage::VerificationFailed(e) => {
                     self.is_verifying = false;
+                    self.pending_server = None;
                     self.app_state = ApplicationState::ServerSelection;
-                    // Clear server config since it failed
-                    self.config.server_address = None;
-                    self.config.server_port = None;
-                    self.config.server_name = None;
                     self.add_notification(
                         format!("Server verification failed: {}", e),
                         NotificationKind::Error,
                     );
                 }
-                AppMessage::MonitorUpdate(msg) => {
-                    self.activity_info.last_update = Some(Instant::now());
-                    self.activity_info.is_playing = msg.contains("Playing");
-                    self.activity_info.is_paused = msg.contains("Paused");
-                    self.activity_info.status = msg;
+                AppMessage::HistoryExported(count, path) => {
+                    self.add_notification(
+                        format!("Exported {} viewings to {}", count, path.display()),
+                        NotificationKind::Success,
+                    );
+                }
+                AppMessage::HistoryExportFailed(e) => {
+                    self.add_notification(
+                        format!("History export failed: {}", e),
+                        NotificationKind::Error,
+                    );
                 }
-                AppMessage::MonitorError(e) => {
-                    log::warn!("Monitor error: {}", e);
-                    self.activity_info.status = format!("Error: Connection issue");
+                AppMessage::StatisticsLoaded(statistics) => {
+                    self.is_loading_statistics = false;
+                    self.statistics = Some(statistics);
+                }
+                AppMessage::StatisticsFailed(e) => {
+                    self.is_loading_statistics = false;
+                    self.add_notification(
+                        format!("Failed to load statistics: {}", e),
+                        NotificationKind::Error,
+                    );
+                }
+                AppMessage::SessionExpired(e) => {
+                    // Only react once, several in-flight tasks may report it
+                    if self.config.auth_token.is_none() {
+                        continue;
+                    }
+                    log::warn!("Plex token rejected: {}", e);
+                    self.is_loading_servers = false;
+                    self.is_verifying = false;
+                    self.pending_server = None;
+                    self.config.auth_token = None;
+                    self.config.username = None;
+                    self.config.user_id = None;
+                    self.dispatch_save_config();
+                    self.activity_info = ActivityInfo::default();
+                    self.app_state = ApplicationState::Login;
+                    self.add_notification(
+                        "Your Plex login expired or was revoked. Please sign in again.".into(),
+                        NotificationKind::Error,
+                    );
                 }
                 AppMessage::ConfigSaved => {}
                 AppMessage::ConfigSaveFailed(e) => {
@@ -381,13 +604,70 @@ impl PlexDiscordApp {
             }
         }
     }
+
+    fn handle_monitor_state(&mut self) {
+        loop {
+            match self.monitor_events.try_recv().map(|e| e.event) {
+                Ok(
+                    event @ (MonitorEvent::DiscordDisconnected { .. }
+                    | MonitorEvent::ServerUnreachable { .. }),
+                ) => {
+                    log::warn!("{}", event);
+                    self.add_notification(event.to_string(), NotificationKind::Error);
+                }
+                Ok(MonitorEvent::IncognitoEnded) => {
+                    if self.config.incognito != Incognito::Off {
+                        self.config.incognito = Incognito::Off;
+                        self.dispatch_save_config();
+                        self.add_notification(
+                            "Incognito ended, your activity is visible again".into(),
+                            NotificationKind::Info,
+                        );
+                    }
+                }
+                Ok(event @ (MonitorEvent::Ended(_) | MonitorEvent::MediaChanged(_))) => {
+                    log::debug!("{}", event);
+                    // A viewing was just recorded
+                    if self.dashboard_tab == DashboardTab::Statistics {
+                        self.dispatch_load_statistics();
+                    }
+                }
+                Ok(event) => log::debug!("{}", event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+
+        if !self.monitor_state.has_changed().unwrap_or(false) {
+            return;
+        }
+        let state = self.monitor_state.borrow_and_update().clone();
+        match state.status {
+            MonitorStatus::Stopped => self.activity_info = ActivityInfo::default(),
+            MonitorStatus::Monitoring => {
+                self.activity_info = ActivityInfo {
+                    session: state.session,
+                    presence_filter: state.presence_filter,
+                    incognito: state.incognito,
+                    last_update: Some(state.updated_at),
+                    discord_connected: state.discord_connected,
+                    unreachable_servers: state.unreachable_servers,
+                };
+            }
+            MonitorStatus::Unauthorized(e) => {
+                self.tx.send(AppMessage::SessionExpired(e)).ok();
+            }
+        }
+    }
 }
 
 // --- UI Implementation ---
 impl eframe::App for PlexDiscordApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // 1. Process Async Messages
+        self.handle_monitor_state();
         self.handle_messages();
+        self.sync_monitor();
 
         // 2. Background Logic Ticks
         match self.app_state {
@@ -399,7 +679,7 @@ impl eframe::App for PlexDiscordApp {
                 ctx.request_repaint_after(Duration::from_millis(100));
             }
             ApplicationState::Running => {
-                self.dispatch_monitor_tick();
+                // Monitoring runs in the background, this only refreshes the dashboard
                 ctx.request_repaint_after(Duration::from_millis(1000));
             }
             _ => {}
@@ -419,6 +699,7 @@ impl eframe::App for PlexDiscordApp {
 
         // 6. Main Content
         egui::CentralPanel::default().show(ctx, |ui| match self.app_state {
+            ApplicationState::ConfigError => self.ui_config_error(ui),
             ApplicationState::Login => self.ui_login(ui),
             ApplicationState::WaitingForAuth => self.ui_waiting_auth(ui),
             ApplicationState::ServerSelection => self.ui_server_selection(ui),
@@ -426,6 +707,16 @@ impl eframe::App for PlexDiscordApp {
             ApplicationState::Verifying => self.ui_verifying(ui),
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // The runtime is dropped with the app, so the writer never sees the monitor stop
+        if let Some(now_playing) = self.now_playing.take() {
+            now_playing.abort();
+            let _ = self.rt.block_on(now_playing);
+            self.rt
+                .block_on(now_playing::clear(&self.config.now_playing));
+        }
+    }
 }
 
 impl PlexDiscordApp {
@@ -454,7 +745,11 @@ impl PlexDiscordApp {
                         ui.colored_label(egui::Color32::from_rgb(76, 175, 80), "Active");
                     }
 
-                    if self.is_loading_servers || self.is_checking_auth || self.is_verifying {
+                    if self.is_loading_servers
+                        || self.is_checking_auth
+                        || self.is_signing_in
+                        || self.is_verifying
+                    {
                         ui.spinner();
                     }
                 });
@@ -526,6 +821,41 @@ impl PlexDiscordApp {
         }
     }
 
+    fn ui_config_error(&mut self, ui: &mut egui::Ui) {
+        ui.centered_and_justified(|ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Settings Could Not Be Loaded");
+                ui.add_space(10.0);
+                if let Some(error) = &self.config_error {
+                    ui.colored_label(egui::Color32::from_rgb(200, 60, 60), error);
+                }
+                ui.add_space(10.0);
+                ui.label("Fix config.json and retry, or start over with fresh settings.");
+                ui.label(
+                    egui::RichText::new("Starting over keeps the old file as config.json.bak.")
+                        .small()
+                        .weak(),
+                );
+                ui.add_space(20.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("🔄 Retry").clicked() {
+                        self.reload_config();
+                    }
+                    if ui.button("Start Over").clicked() {
+                        match self.config_store.reset() {
+                            Ok(()) => self.reload_config(),
+                            Err(e) => self.add_notification(
+                                format!("Could not move settings aside: {}", e),
+                                NotificationKind::Error,
+                            ),
+                        }
+                    }
+                });
+            });
+        });
+    }
+
     fn ui_login(&mut self, ui: &mut egui::Ui) {
         ui.centered_and_justified(|ui| {
             ui.vertical_centered(|ui| {
@@ -539,6 +869,52 @@ impl PlexDiscordApp {
                 if ui.add(btn).clicked() {
                     self.dispatch_start_login();
                 }
+
+                ui.add_space(20.0);
+                egui::CollapsingHeader::new("🔑 Sign in with username & password")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new("For machines where a browser can't be opened.")
+                                .small()
+                                .weak(),
+                        );
+                        egui::Frame::group(ui.style()).show(ui, |ui| {
+                            egui::Grid::new("credentials_grid")
+                                .num_columns(2)
+                                .spacing([10.0, 10.0])
+                                .show(ui, |ui| {
+                                    ui.label("Username / Email:");
+                                    ui.text_edit_singleline(&mut self.login_username);
+                                    ui.end_row();
+
+                                    ui.label("Password:");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.login_password)
+                                            .password(true),
+                                    );
+                                    ui.end_row();
+
+                                    ui.label("2FA Code:");
+                                    ui.text_edit_singleline(&mut self.login_verification_code)
+                                        .on_hover_text("Leave empty if two-factor is off");
+                                    ui.end_row();
+                                });
+
+                            ui.add_space(5.0);
+                            let sign_in = ui.add_enabled(
+                                !self.is_signing_in,
+                                egui::Button::new(if self.is_signing_in {
+                                    "⏳ Signing in..."
+                                } else {
+                                    "Sign In"
+                                }),
+                            );
+                            if sign_in.clicked() {
+                                self.dispatch_credentials_login();
+                            }
+                        });
+                    });
             });
         });
     }
@@ -580,25 +956,20 @@ impl PlexDiscordApp {
                 ui.add_space(20.0);
                 ui.heading("Verifying Connection");
 
-                if let Some(name) = &self.config.server_name {
-                    ui.label(format!("Connecting to: {}", name));
-                }
-
-                if let Some(addr) = &self.config.server_address {
-                    if let Some(port) = self.config.server_port {
-                        ui.label(
-                            egui::RichText::new(format!("{}:{}", addr, port))
-                                .weak()
-                                .small(),
-                        );
-                    }
+                if let Some(server) = &self.pending_server {
+                    ui.label(format!("Connecting to: {}", server.name));
+                    ui.label(
+                        egui::RichText::new(format!("{}:{}", server.address, server.port))
+                            .weak()
+                            .small(),
+                    );
                 }
 
                 ui.add_space(30.0);
                 if ui.button("Cancel").clicked() {
                     self.is_verifying = false;
+                    self.pending_server = None;
                     self.app_state = ApplicationState::ServerSelection;
-                    self.config.server_address = None;
                 }
             });
         });
@@ -610,10 +981,17 @@ impl PlexDiscordApp {
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if ui.button("🚪 Logout").clicked() {
                     self.dispatch_disconnect();
-                    self.config = AppConfig::default();
+                    // Only the account goes, filters and other preferences stay for the next login
+                    self.config.auth_token = None;
+                    self.config.username = None;
+                    self.config.user_id = None;
+                    self.servers.clear();
                     self.dispatch_save_config();
                     self.app_state = ApplicationState::Login;
                 }
+                if !self.config.servers.is_empty() && ui.button("⬅ Back").clicked() {
+                    self.app_state = ApplicationState::Running;
+                }
                 if ui
                     .button(if self.is_loading_servers {
                         "⏳ Fetching..."
@@ -726,7 +1104,11 @@ impl PlexDiscordApp {
                     });
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.button("Connect").clicked() {
+                        let is_monitored =
+                            self.config.servers.iter().any(|s| s.is_same_server(server));
+                        if is_monitored {
+                            ui.label(egui::RichText::new("Monitoring").weak());
+                        } else if ui.button("Connect").clicked() {
                             self.connect_to_server_auto(idx);
                         }
                     });
@@ -736,20 +1118,33 @@ impl PlexDiscordApp {
     }
 
     fn ui_dashboard(&mut self, ui: &mut egui::Ui) {
-        let server_name = self
-            .config
-            .server_name
-            .clone()
-            .unwrap_or("Unknown Server".to_string());
-        let server_addr = self
-            .config
-            .server_address
-            .clone()
-            .unwrap_or("Unknown Address".to_string());
-        let server_port = self.config.server_port.unwrap_or(32400);
+        ui.horizontal(|ui| {
+            let previous = self.dashboard_tab;
+            ui.selectable_value(
+                &mut self.dashboard_tab,
+                DashboardTab::Overview,
+                "🏠 Overview",
+            );
+            ui.selectable_value(
+                &mut self.dashboard_tab,
+                DashboardTab::Statistics,
+                "📊 Statistics",
+            );
+            if previous != self.dashboard_tab && self.dashboard_tab == DashboardTab::Statistics {
+                self.dispatch_load_statistics();
+            }
+        });
+        ui.separator();
+
+        match self.dashboard_tab {
+            DashboardTab::Overview => self.ui_overview(ui),
+            DashboardTab::Statistics => self.ui_statistics(ui),
+        }
+    }
 
+    fn ui_overview(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical().show(ui, |ui| {
-            // --- Connection Status Card ---
+            // --- Servers Card ---
             egui::Frame::group(ui.style())
                 .inner_margin(16.0)
                 .corner_radius(8)
@@ -757,19 +1152,101 @@ impl PlexDiscordApp {
                 .show(ui, |ui| {
                     ui.set_width(ui.available_width());
                     ui.horizontal(|ui| {
-                        ui.label(egui::RichText::new("🌐").size(32.0));
-                        ui.vertical(|ui| {
-                            ui.heading(&server_name);
-                            ui.label(
-                                egui::RichText::new(format!("{}:{}", server_addr, server_port))
-                                    .monospace()
-                                    .weak(),
-                            );
-                        });
+                        ui.heading("Servers");
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            ui.colored_label(egui::Color32::from_rgb(76, 175, 80), "Connected");
+                            if ui.button("➕ Add Server").clicked() {
+                                self.app_state = ApplicationState::ServerSelection;
+                                if self.servers.is_empty() {
+                                    self.dispatch_fetch_servers();
+                                }
+                            }
                         });
                     });
+                    ui.separator();
+
+                    let server_count = self.config.servers.len();
+                    let mut action: Option<ServerListAction> = None;
+
+                    for (idx, server) in self.config.servers.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("🌐").size(24.0));
+                            ui.vertical(|ui| {
+                                ui.label(egui::RichText::new(&server.name).strong());
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "{}:{}",
+                                            server.address, server.port
+                                        ))
+                                        .monospace()
+                                        .weak(),
+                                    );
+                                    if server.owned {
+                                        ui.label(egui::RichText::new("Owned (Polling)").small());
+                                    } else {
+                                        ui.label(egui::RichText::new("Shared (WebSocket)").small());
+                                    }
+                                });
+                            });
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui.button("🗑").on_hover_text("Remove").clicked() {
+                                        action = Some(ServerListAction::Remove(idx));
+                                    }
+                                    if server_count > 1 {
+                                        if ui
+                                            .add_enabled(
+                                                idx + 1 < server_count,
+                                                egui::Button::new("⬇"),
+                                            )
+                                            .clicked()
+                                        {
+                                            action = Some(ServerListAction::MoveDown(idx));
+                                        }
+                                        if ui.add_enabled(idx > 0, egui::Button::new("⬆")).clicked()
+                                        {
+                                            action = Some(ServerListAction::MoveUp(idx));
+                                        }
+                                    }
+                                },
+                            );
+                        });
+                    }
+
+                    if server_count > 1 {
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("When several are playing:").small());
+                            let before = self.config.multi_server_policy;
+                            egui::ComboBox::from_id_salt("multi_server_policy")
+                                .selected_text(match before {
+                                    MultiServerPolicy::MostRecentlyStarted => {
+                                        "Most recently started"
+                                    }
+                                    MultiServerPolicy::ServerOrder => "First in list",
+                                })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.config.multi_server_policy,
+                                        MultiServerPolicy::MostRecentlyStarted,
+                                        "Most recently started",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.config.multi_server_policy,
+                                        MultiServerPolicy::ServerOrder,
+                                        "First in list",
+                                    );
+                                });
+                            if self.config.multi_server_policy != before {
+                                self.dispatch_save_config();
+                            }
+                        });
+                    }
+
+                    if let Some(action) = action {
+                        self.apply_server_list_action(action);
+                    }
                 });
 
             // --- Activity Monitor Card ---
@@ -800,33 +1277,137 @@ impl PlexDiscordApp {
 
                     // Status display with icon
                     ui.horizontal(|ui| {
-                        let (status_icon, status_color) = if self.activity_info.is_playing {
-                            ("▶", egui::Color32::from_rgb(76, 175, 80))
-                        } else if self.activity_info.is_paused {
-                            ("⏸", egui::Color32::from_rgb(255, 193, 7))
-                        } else {
-                            ("⏹", ui.visuals().text_color())
+                        let session = self.activity_info.session.as_ref();
+                        let (status_icon, status_color, status) = match session {
+                            Some(s) => match s.player_state {
+                                PlayerState::Paused => (
+                                    "⏸",
+                                    egui::Color32::from_rgb(255, 193, 7),
+                                    format!("Paused: {}", s.media_title),
+                                ),
+                                PlayerState::Buffering => (
+                                    "⏳",
+                                    ui.visuals().text_color(),
+                                    format!("Buffering: {}", s.media_title),
+                                ),
+                                _ => (
+                                    "▶",
+                                    egui::Color32::from_rgb(76, 175, 80),
+                                    format!("Playing: {}", s.media_title),
+                                ),
+                            },
+                            None => (
+                                "⏹",
+                                ui.visuals().text_color(),
+                                "No active session".to_string(),
+                            ),
                         };
 
                         ui.label(egui::RichText::new(status_icon).size(20.0));
+                        ui.colored_label(status_color, egui::RichText::new(status).size(15.0));
+                    });
+
+                    if let Some(show) = self
+                        .activity_info
+                        .session
+                        .as_ref()
+                        .and_then(|s| s.media_grandparent_title.as_ref())
+                    {
+                        ui.label(egui::RichText::new(show).weak());
+                    }
+
+                    if let Some(player) = self
+                        .activity_info
+                        .session
+                        .as_ref()
+                        .and_then(|s| s.player.as_ref())
+                    {
+                        let name = match (player.name(), &player.platform) {
+                            (Some(name), Some(platform)) if name != platform => {
+                                format!("{} ({})", name, platform)
+                            }
+                            (Some(name), _) => name.to_string(),
+                            (None, _) => player.machine_identifier.clone().unwrap_or_default(),
+                        };
+                        if !name.is_empty() {
+                            ui.label(egui::RichText::new(format!("📺 on {}", name)).weak());
+                        }
+                    }
+
+                    match self.activity_info.presence_filter {
+                        _ if self.activity_info.incognito => {}
+                        Some(FilterAction::Hide) => {
+                            ui.label(
+                                egui::RichText::new("🔒 Hidden from Discord by privacy filters")
+                                    .weak(),
+                            );
+                        }
+                        Some(FilterAction::Generic) => {
+                            let shown = self
+                                .activity_info
+                                .session
+                                .as_ref()
+                                .map(|s| generic_session(s).media_title)
+                                .unwrap_or_default();
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "🔒 Shown on Discord as \"{}\"",
+                                    shown
+                                ))
+                                .weak(),
+                            );
+                        }
+                        None => {}
+                    }
+
+                    if self.activity_info.last_update.is_some()
+                        && !self.activity_info.discord_connected
+                    {
                         ui.colored_label(
-                            status_color,
-                            egui::RichText::new(&self.activity_info.status).size(15.0),
+                            egui::Color32::from_rgb(200, 60, 60),
+                            "Discord is not connected",
                         );
-                    });
+                    }
+                    if !self.activity_info.unreachable_servers.is_empty() {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(200, 60, 60),
+                            format!(
+                                "Unreachable: {}",
+                                self.activity_info.unreachable_servers.join(", ")
+                            ),
+                        );
+                    }
 
-                    ui.add_space(8.0);
+                    ui.separator();
+                    if let Some(incognito) = self.ui_incognito(ui) {
+                        self.config.incognito = incognito;
+                        self.dispatch_save_config();
+                    }
+                });
 
-                    // Additional info
+            ui.add_space(20.0);
+
+            // --- History Card ---
+            egui::Frame::group(ui.style())
+                .inner_margin(16.0)
+                .corner_radius(8)
+                .fill(ui.style().visuals.faint_bg_color)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.heading("Watch History");
+                    ui.separator();
                     ui.horizontal(|ui| {
-                        ui.label(egui::RichText::new("Server Type:").weak().small());
-                        if let Some(owned) = self.config.is_owned {
-                            if owned {
-                                ui.label(egui::RichText::new("Owned (Polling)").small());
-                            } else {
-                                ui.label(egui::RichText::new("Shared (WebSocket)").small());
+                        ui.label(
+                            egui::RichText::new("Finished viewings are recorded locally").weak(),
+                        );
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("Export JSON").clicked() {
+                                self.dispatch_export_history(ExportFormat::Json);
                             }
-                        }
+                            if ui.button("Export CSV").clicked() {
+                                self.dispatch_export_history(ExportFormat::Csv);
+                            }
+                        });
                     });
                 });
 
@@ -836,7 +1417,7 @@ impl PlexDiscordApp {
             ui.vertical_centered(|ui| {
                 if ui
                     .add(
-                        egui::Button::new("Disconnect Server")
+                        egui::Button::new("Disconnect All")
                             .fill(egui::Color32::from_rgb(80, 40, 40))
                             .stroke(egui::Stroke::NONE)
                             .min_size(egui::vec2(150.0, 35.0)),
@@ -851,42 +1432,444 @@ impl PlexDiscordApp {
         });
     }
 
+    /// Incognito status and controls. Returns the new setting when it was changed.
+    fn ui_incognito(&self, ui: &mut egui::Ui) -> Option<Incognito> {
+        let mut changed = None;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("🕶").size(16.0));
+            let status = match &self.config.incognito {
+                Incognito::Off => None,
+                Incognito::On => Some("Incognito, Discord shows nothing".to_string()),
+                Incognito::Until { until } => {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or(Duration::ZERO)
+                        .as_secs();
+                    let minutes = until.saturating_sub(now).div_ceil(60);
+                    Some(format!("Incognito for {} more min", minutes))
+                }
+                Incognito::UntilMediaEnds { media_title, .. } => {
+                    Some(format!("Incognito until {} ends", media_title))
+                }
+            };
+
+            match status {
+                Some(status) => {
+                    ui.label(egui::RichText::new(status).strong());
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Show Activity").clicked() {
+                            changed = Some(Incognito::Off);
+                        }
+                    });
+                }
+                None => {
+                    ui.label("Incognito");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let session = self.activity_info.session.as_ref();
+                        if ui
+                            .add_enabled(session.is_some(), egui::Button::new("Until This Ends"))
+                            .clicked()
+                        {
+                            changed = session.map(Incognito::until_media_ends);
+                        }
+                        if ui.button("30 min").clicked() {
+                            let now = std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .unwrap_or(Duration::ZERO)
+                                .as_secs();
+                            changed = Some(Incognito::Until {
+                                until: now + 30 * 60,
+                            });
+                        }
+                        if ui.button("On").clicked() {
+                            changed = Some(Incognito::On);
+                        }
+                    });
+                }
+            }
+        });
+        changed
+    }
+
+    fn ui_statistics(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Statistics");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if self.is_loading_statistics {
+                    ui.spinner();
+                } else if ui.button("🔄 Refresh").clicked() {
+                    self.dispatch_load_statistics();
+                }
+            });
+        });
+
+        let Some(stats) = &self.statistics else {
+            return;
+        };
+        if stats.viewings == 0 {
+            ui.add_space(40.0);
+            ui.vertical_centered(|ui| {
+                ui.label(egui::RichText::new("No watch history yet").weak());
+                ui.label(
+                    egui::RichText::new("Viewings show up here once they have been recorded")
+                        .weak()
+                        .small(),
+                );
+            });
+            return;
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            // --- Totals ---
+            ui.horizontal(|ui| {
+                let today = stats.per_day.last().map_or(0, |d| d.watched_ms);
+                let this_week = stats.per_week.last().map_or(0, |w| w.watched_ms);
+                for (value, caption) in [
+                    (
+                        format_watch_time(stats.total_watched_ms),
+                        "watched in total",
+                    ),
+                    (stats.viewings.to_string(), "viewings"),
+                    (stats.finished.to_string(), "finished"),
+                    (format_watch_time(this_week), "this week"),
+                    (format_watch_time(today), "today"),
+                ] {
+                    ui.vertical(|ui| {
+                        ui.label(egui::RichText::new(value).size(20.0).strong());
+                        ui.label(egui::RichText::new(caption).weak().small());
+                    });
+                    ui.add_space(12.0);
+                }
+            });
+
+            ui.add_space(12.0);
+            stats_card(ui, "Per Day", |ui| {
+                let bars: Vec<_> = stats
+                    .per_day
+                    .iter()
+                    .map(|day| period_bar(day, "%-d", "%a, %b %-d"))
+                    .collect();
+                bar_chart(ui, &bars, egui::Color32::from_rgb(229, 160, 13));
+            });
+
+            ui.add_space(12.0);
+            stats_card(ui, "Per Week", |ui| {
+                let bars: Vec<_> = stats
+                    .per_week
+                    .iter()
+                    .map(|week| period_bar(week, "%b %-d", "Week of %b %-d"))
+                    .collect();
+                bar_chart(ui, &bars, egui::Color32::from_rgb(88, 101, 242));
+            });
+
+            ui.add_space(12.0);
+            stats_card(ui, "By Media Type", |ui| {
+                media_type_split(ui, &stats.by_media_type);
+            });
+
+            for (heading, list, media_type) in [
+                ("Top Shows", &stats.top_shows, MediaType::Episode),
+                ("Top Movies", &stats.top_movies, MediaType::Movie),
+                ("Top Artists", &stats.top_artists, MediaType::Track),
+            ] {
+                if list.is_empty() {
+                    continue;
+                }
+                ui.add_space(12.0);
+                stats_card(ui, heading, |ui| {
+                    top_list(ui, list, media_type_color(&media_type));
+                });
+            }
+
+            ui.add_space(20.0);
+        });
+    }
+
     // --- State Transitions ---
 
     fn connect_to_server_auto(&mut self, idx: usize) {
-        if let Some(server) = self.servers.get(idx) {
-            self.config.server_name = Some(server.name.clone());
-            self.config.server_address = Some(server.address.clone());
-            self.config.server_port = Some(server.port);
-            self.config.is_owned = Some(server.owned);
+        if let Some(server) = self.servers.get(idx).cloned() {
+            self.pending_server = Some(server.clone());
             self.app_state = ApplicationState::Verifying;
-            self.dispatch_verify_connection();
+            self.dispatch_verify_connection(server);
         }
     }
 
     fn connect_to_server_manual(&mut self, ip: String, port: u16, owned: bool) {
-        self.config.server_name = Some("Custom Server".to_string());
-        self.config.server_address = Some(ip);
-        self.config.server_port = Some(port);
-        self.config.is_owned = Some(owned);
+        let server = PlexServer {
+            name: "Custom Server".to_string(),
+            address: ip,
+            port,
+            owned,
+            uri: None,
+            machine_identifier: None,
+            connections: Vec::new(),
+        };
+        self.pending_server = Some(server.clone());
         self.app_state = ApplicationState::Verifying;
-        self.dispatch_verify_connection();
+        self.dispatch_verify_connection(server);
     }
 
-    fn dispatch_disconnect(&mut self) {
-        let monitor = self.monitor_service.clone();
-        let _config = self.config.clone();
-        self.rt.spawn(async move {
-            let mut service = monitor.lock().await;
-            let _ = service.clear_state().await;
+    fn reload_config(&mut self) {
+        match self.config_store.load() {
+            Ok(config) => {
+                self.app_state = Self::initial_state(&config);
+                self.config = config;
+                self.config_error = None;
+                self.rebuild_services();
+                if self.config.is_authenticated() && self.config.user_id.is_none() {
+                    self.dispatch_fetch_account();
+                }
+                if self.app_state == ApplicationState::ServerSelection {
+                    self.dispatch_fetch_servers();
+                }
+            }
+            Err(e) => self.config_error = Some(e.to_string()),
+        }
+    }
+
+    fn apply_server_list_action(&mut self, action: ServerListAction) {
+        match action {
+            ServerListAction::MoveUp(idx) => self.config.servers.swap(idx, idx - 1),
+            ServerListAction::MoveDown(idx) => self.config.servers.swap(idx, idx + 1),
+            ServerListAction::Remove(idx) => {
+                let server = self.config.servers.remove(idx);
+                if self.config.servers.is_empty() {
+                    self.dispatch_disconnect();
+                    return;
+                }
+                self.add_notification(
+                    format!("Stopped monitoring {}", server.name),
+                    NotificationKind::Info,
+                );
+            }
+        }
+        self.dispatch_save_config();
+    }
+
+    fn dispatch_export_history(&self, format: ExportFormat) {
+        let history_service = self.history_service.clone();
+        let tx = self.tx.clone();
+        let dir = directories::UserDirs::new()
+            .and_then(|dirs| dirs.download_dir().map(|d| d.to_path_buf()))
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let path = dir.join(format!("plex-history.{}", format.extension()));
+
+        self.rt.spawn_blocking(move || {
+            let message = match history_service.export(&path, format) {
+                Ok(count) => AppMessage::HistoryExported(count, path),
+                Err(e) => AppMessage::HistoryExportFailed(e.to_string()),
+            };
+            let _ = tx.send(message);
+        });
+    }
+
+    fn dispatch_load_statistics(&mut self) {
+        if self.is_loading_statistics {
+            return;
+        }
+        self.is_loading_statistics = true;
+        let history_service = self.history_service.clone();
+        let tx = self.tx.clone();
+
+        self.rt.spawn_blocking(move || {
+            let message = match history_service.statistics() {
+                Ok(statistics) => AppMessage::StatisticsLoaded(statistics),
+                Err(e) => AppMessage::StatisticsFailed(e.to_string()),
+            };
+            let _ = tx.send(message);
         });
+    }
 
+    fn dispatch_disconnect(&mut self) {
+        // Clearing the servers stops the monitor task on the next frame
         self.app_state = ApplicationState::ServerSelection;
-        self.config.server_address = None;
-        self.config.server_port = None;
-        self.config.server_name = None;
+        self.config.servers.clear();
         self.activity_info = ActivityInfo::default();
         self.dispatch_save_config();
         self.add_notification("Disconnected".into(), NotificationKind::Info);
     }
 }
+
+// --- Statistics Charts ---
+
+fn stats_card(ui: &mut egui::Ui, heading: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    egui::Frame::group(ui.style())
+        .inner_margin(16.0)
+        .corner_radius(8)
+        .fill(ui.style().visuals.faint_bg_color)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.label(egui::RichText::new(heading).strong());
+            ui.add_space(4.0);
+            add_contents(ui);
+        });
+}
+
+/// One bar of a chart: axis label, hover text and value.
+struct Bar {
+    label: String,
+    hover: String,
+    value: u64,
+}
+
+fn period_bar(period: &PeriodTotal, label: &str, hover: &str) -> Bar {
+    Bar {
+        label: period.start.strftime(label).to_string(),
+        hover: format!(
+            "{}: {}",
+            period.start.strftime(hover),
+            format_watch_time(period.watched_ms)
+        ),
+        value: period.watched_ms,
+    }
+}
+
+fn bar_chart(ui: &mut egui::Ui, bars: &[Bar], color: egui::Color32) {
+    const CHART_HEIGHT: f32 = 120.0;
+    const LABEL_HEIGHT: f32 = 16.0;
+
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), CHART_HEIGHT + LABEL_HEIGHT),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    let baseline = rect.top() + CHART_HEIGHT;
+    let max = bars.iter().map(|b| b.value).max().unwrap_or(0).max(1);
+    let slot = rect.width() / bars.len().max(1) as f32;
+    let pointer = response.hover_pos();
+    let mut hovered = None;
+
+    for (idx, bar) in bars.iter().enumerate() {
+        let left = rect.left() + slot * idx as f32;
+        let column = egui::Rect::from_min_max(
+            egui::pos2(left, rect.top()),
+            egui::pos2(left + slot, rect.bottom()),
+        );
+        let is_hovered = pointer.is_some_and(|p| column.contains(p));
+        if is_hovered {
+            painter.rect_filled(column, 2.0, ui.visuals().widgets.hovered.weak_bg_fill);
+            hovered = Some(bar);
+        }
+
+        let height = (CHART_HEIGHT - 4.0) * (bar.value as f32 / max as f32);
+        let filled = egui::Rect::from_min_max(
+            egui::pos2(left + slot * 0.15, baseline - height),
+            egui::pos2(left + slot * 0.85, baseline),
+        );
+        painter.rect_filled(filled, 2.0, color);
+        painter.text(
+            egui::pos2(column.center().x, baseline + 2.0),
+            egui::Align2::CENTER_TOP,
+            &bar.label,
+            egui::FontId::proportional(10.0),
+            ui.visuals().weak_text_color(),
+        );
+    }
+
+    painter.hline(
+        rect.x_range(),
+        baseline,
+        ui.visuals().widgets.noninteractive.bg_stroke,
+    );
+    if let Some(bar) = hovered {
+        response.on_hover_text_at_pointer(&bar.hover);
+    }
+}
+
+/// A single stacked bar with a legend below it.
+fn media_type_split(ui: &mut egui::Ui, totals: &[(MediaType, u64)]) {
+    let total: u64 = totals.iter().map(|(_, ms)| ms).sum();
+    if total == 0 {
+        return;
+    }
+
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 18.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let mut left = rect.left();
+    for (media_type, ms) in totals {
+        let width = rect.width() * (*ms as f32 / total as f32);
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(left, rect.top()),
+                egui::pos2(left + width, rect.bottom()),
+            ),
+            0.0,
+            media_type_color(media_type),
+        );
+        left += width;
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        for (media_type, ms) in totals {
+            ui.colored_label(media_type_color(media_type), "⏹");
+            ui.label(format!(
+                "{} {} ({:.0}%)",
+                media_type_name(media_type),
+                format_watch_time(*ms),
+                *ms as f64 * 100.0 / total as f64
+            ));
+            ui.add_space(8.0);
+        }
+    });
+}
+
+fn top_list(ui: &mut egui::Ui, list: &[TitleTotal], color: egui::Color32) {
+    let max = list.iter().map(|t| t.watched_ms).max().unwrap_or(0).max(1);
+    for (idx, entry) in list.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("{}.", idx + 1)).weak());
+            ui.label(&entry.title);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let plays = if entry.plays == 1 { "play" } else { "plays" };
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} · {} {}",
+                        format_watch_time(entry.watched_ms),
+                        entry.plays,
+                        plays
+                    ))
+                    .weak(),
+                );
+            });
+        });
+        let (rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 4.0), egui::Sense::hover());
+        let width = rect.width() * (entry.watched_ms as f32 / max as f32);
+        ui.painter().rect_filled(
+            egui::Rect::from_min_size(rect.min, egui::vec2(width, rect.height())),
+            2.0,
+            color,
+        );
+    }
+}
+
+fn media_type_color(media_type: &MediaType) -> egui::Color32 {
+    match media_type {
+        MediaType::Episode => egui::Color32::from_rgb(88, 101, 242),
+        MediaType::Movie => egui::Color32::from_rgb(229, 160, 13),
+        MediaType::Track => egui::Color32::from_rgb(76, 175, 80),
+        MediaType::Unknown | MediaType::Idle => egui::Color32::GRAY,
+    }
+}
+
+fn media_type_name(media_type: &MediaType) -> &'static str {
+    match media_type {
+        MediaType::Episode => "TV",
+        MediaType::Movie => "Movies",
+        MediaType::Track => "Music",
+        MediaType::Unknown | MediaType::Idle => "Other",
+    }
+}
+
+/// `12h 05m` for an hour or more, otherwise `45m`.
+fn format_watch_time(millis: u64) -> String {
+    let minutes = millis / 60_000;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}